@@ -0,0 +1,92 @@
+/*
+    Compares `parse_replay` with the zero-copy `parse_replay_bytes` on the bundled replays, both decode every block & action.
+
+    Needs nightly for `#[bench]`:
+    > cargo +nightly bench -p w3g-common
+*/
+#![feature(test)]
+
+extern crate test;
+extern crate w3g_common;
+
+use w3g_common::parser::{ReplayBlockView, ActionView, ParseWarning};
+
+use std::fs::File;
+use std::io::{Cursor, Read};
+
+use test::Bencher;
+
+const REPLAYS: [&'static str; 4] = [
+    "resources/11379705.w3g",
+    "resources/11151616.w3g",
+    "resources/11151801.w3g",
+    "resources/11151811.w3g",
+];
+
+fn read_replays() -> Vec<Vec<u8>>
+{
+    REPLAYS.iter()
+        .map(|path|
+        {
+            let mut raw = Vec::new();
+            File::open(path).unwrap().read_to_end(&mut raw).unwrap();
+            raw
+        })
+        .collect()
+}
+
+/// `b.bytes` is the size of the compressed replays so the throughputs are comparable
+fn total_size(replays: &Vec<Vec<u8>>) -> u64
+{
+    replays.iter().map(|raw| raw.len() as u64).sum()
+}
+
+#[bench]
+fn bench_parse_replay(b: &mut Bencher)
+{
+    let replays = read_replays();
+    b.bytes = total_size(&replays);
+
+    b.iter(||
+    {
+        for raw in replays.iter()
+        {
+            test::black_box(w3g_common::parser::parse_replay(&mut Cursor::new(&raw[..])).unwrap());
+        }
+    });
+}
+
+/// Keeps every block & action like `parse_replay` does, just borrowed
+#[bench]
+fn bench_parse_replay_bytes(b: &mut Bencher)
+{
+    let replays = read_replays();
+    b.bytes = total_size(&replays);
+
+    b.iter(||
+    {
+        for raw in replays.iter()
+        {
+            let data = w3g_common::parser::parse_replay_bytes(raw).unwrap();
+            let blocks = data.blocks()
+                .map(|block|
+                {
+                    let block = block.unwrap();
+                    let actions = match block
+                    {
+                        ReplayBlockView::Tick { ref commands, .. } | ReplayBlockView::TickPreOverflow { ref commands, .. } =>
+                        {
+                            commands.iter()
+                                .map(|command| command.actions().unwrap())
+                                .collect()
+                        },
+                        _ => Vec::new(),
+                    };
+
+                    (block, actions)
+                })
+                .collect::<Vec<(ReplayBlockView, Vec<(Vec<ActionView>, Vec<ParseWarning>)>)>>();
+            test::black_box(blocks);
+        }
+    });
+}
@@ -0,0 +1,211 @@
+/*
+    Actions per minute, counted per player over the time they were in the game.
+
+    Only actions a player makes with the mouse or keyboard are counted, anything the game or map sends on their
+    behalf (syncing the game cache, trigger events, ...) isn't. Effective actions also leave out spam:
+        - an action identical to the player's previous one within `ApmOptions.repeat_window`, e.g. double tapping a group
+        - opening the build or hero skill menus, what's built or learned is counted instead
+        - pressing escape
+*/
+
+use parser::{Replay, Action, TimelineEvent, ReplayBlock};
+use super::{player_records, end_time};
+
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone, new)]
+pub struct ApmOptions
+{
+    /* Milliseconds of game time covered by each `ApmBucket` */
+    pub bucket_size: u32,
+    /* Milliseconds within which a repeated action is considered spam */
+    pub repeat_window: u32,
+}
+
+/// One bucket per minute and repeats within half a second are spam
+impl Default for ApmOptions
+{
+    fn default() -> ApmOptions
+    {
+        ApmOptions {
+            bucket_size: 60 * 1000,
+            repeat_window: 500,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct ApmBucket
+{
+    /* Milliseconds of game time the bucket starts at */
+    pub start: u32,
+    /* Milliseconds of the bucket the player was in the game for, shorter than `bucket_size` for their last bucket */
+    pub duration: u32,
+    pub actions: u32,
+    pub effective_actions: u32,
+    pub apm: f64,
+    pub eapm: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct PlayerApm
+{
+    pub player_id: u8,
+    pub player_name: String,
+    /* Milliseconds of game time until the player left or the replay ended */
+    pub time_played: u32,
+    pub actions: u32,
+    pub effective_actions: u32,
+    pub apm: f64,
+    pub eapm: f64,
+    pub buckets: Vec<ApmBucket>,
+}
+
+/// Whether the player made the action themselves
+pub fn is_player_action(action: &Action) -> bool
+{
+    match *action
+    {
+        Action::SelfOrder { .. } |
+        Action::PointOrder { .. } |
+        Action::ObjectOrder { .. } |
+        Action::DropOrGiveItem { .. } |
+        Action::FogObjectOrder { .. } |
+        Action::ChangeSelection { .. } |
+        Action::AssignGroup { .. } |
+        Action::SelectGroup { .. } |
+        Action::SelectSubGroup { .. } |
+        Action::SelectSubGroupIndex { .. } |
+        Action::SelectGroundItem { .. } |
+        Action::CancelHeroRevival { .. } |
+        Action::CancelUnitInQueue { .. } |
+        Action::TransferResources { .. } |
+        Action::Esc() |
+        Action::EnterHeroSkillSubMenu() |
+        Action::EnterBuildingSubMenu() |
+        Action::MiniMapSignal { .. } => true,
+        _ => false,
+    }
+}
+
+/// Whether a player action does something, `previous` is the player's last action & how long ago it was
+fn is_effective_action(action: &Action, previous: Option<(&Action, u32)>, options: &ApmOptions) -> bool
+{
+    match *action
+    {
+        Action::Esc() | Action::EnterHeroSkillSubMenu() | Action::EnterBuildingSubMenu() => return false,
+        _ => {},
+    }
+
+    match previous
+    {
+        Some((previous, elapsed)) => previous != action || elapsed > options.repeat_window,
+        None => true,
+    }
+}
+
+/// Per minute over `duration` milliseconds
+fn per_minute(count: u32, duration: u32) -> f64
+{
+    if duration == 0
+    {
+        0.0
+    } else
+    {
+        (count as f64) * 60000.0 / (duration as f64)
+    }
+}
+
+pub fn player_apm(replay: &Replay) -> Vec<PlayerApm>
+{
+    player_apm_with_options(replay, ApmOptions::default())
+}
+
+/// APM & EAPM for every player in the `GameHeader`, the replay saver first.
+///
+/// Actions made while the game is paused aren't counted since no game time passes.
+pub fn player_apm_with_options(replay: &Replay, options: ApmOptions) -> Vec<PlayerApm>
+{
+    let timeline = replay.timeline();
+    let end = end_time(&timeline);
+    let bucket_size = options.bucket_size.max(1);
+
+    player_records(replay).into_iter()
+        .map(|player|
+        {
+            let time_played = timeline.iter()
+                .filter_map(|entry| match entry.event
+                {
+                    TimelineEvent::Block(&ReplayBlock::LeaveGame { player_id, .. }) if player_id == player.player_id => Some(entry.time),
+                    _ => None,
+                })
+                .next()
+                .unwrap_or(end);
+
+            let number_of_buckets = (time_played + bucket_size - 1) / bucket_size;
+            let mut buckets = (0..number_of_buckets)
+                .map(|index|
+                {
+                    let start = index * bucket_size;
+                    ApmBucket {
+                        start,
+                        duration: bucket_size.min(time_played - start),
+                        actions: 0,
+                        effective_actions: 0,
+                        apm: 0.0,
+                        eapm: 0.0,
+                    }
+                })
+                .collect::<Vec<ApmBucket>>();
+
+            let mut actions = 0;
+            let mut effective_actions = 0;
+            let mut previous: Option<(&Action, u32)> = None;
+
+            for entry in timeline.iter().filter(|entry| !entry.paused)
+            {
+                let action = match entry.event
+                {
+                    TimelineEvent::Action { player_id, action } if player_id == player.player_id && is_player_action(action) => action,
+                    _ => continue,
+                };
+
+                let effective = is_effective_action(action, previous.map(|(previous, time)| (previous, entry.time - time)), &options);
+                previous = Some((action, entry.time));
+
+                actions = actions + 1;
+                if effective
+                {
+                    effective_actions = effective_actions + 1;
+                }
+
+                /* Commands are stamped with the end of their tick so the last one can land exactly on `time_played` */
+                let index = (entry.time / bucket_size) as usize;
+                if let Some(bucket) = buckets.get_mut(index.min(number_of_buckets.saturating_sub(1) as usize))
+                {
+                    bucket.actions = bucket.actions + 1;
+                    if effective
+                    {
+                        bucket.effective_actions = bucket.effective_actions + 1;
+                    }
+                }
+            }
+
+            for bucket in buckets.iter_mut()
+            {
+                bucket.apm = per_minute(bucket.actions, bucket.duration);
+                bucket.eapm = per_minute(bucket.effective_actions, bucket.duration);
+            }
+
+            PlayerApm {
+                player_id: player.player_id,
+                player_name: player.player_name.clone(),
+                time_played,
+                actions,
+                effective_actions,
+                apm: per_minute(actions, time_played),
+                eapm: per_minute(effective_actions, time_played),
+                buckets,
+            }
+        })
+        .collect()
+}
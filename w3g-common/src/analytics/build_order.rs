@@ -0,0 +1,116 @@
+/*
+    What every player built & trained in the order they gave the orders.
+
+    Building and training use the rawcode of what's being made as the order id, with the `Construct` flag set when
+    placing a structure and `Train` when queueing a unit. These are orders rather than completions: a structure that
+    was never started or a unit that got cancelled still shows up, cancellations are reported separately.
+*/
+
+use parser::{Replay, Action, OrderType, TimelineEvent, Dictionary};
+use parser::dictionary::id_to_rawcode;
+use super::player_records;
+
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum BuildOrderKind
+{
+    /* A structure was placed, always a `PointOrder` */
+    Construct,
+    /* A unit was queued */
+    Train,
+    /* `CancelUnitInQueue` */
+    Cancel,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct BuildOrderEntry
+{
+    /* Milliseconds of game time, see `Replay::timeline` */
+    pub time: u32,
+    pub kind: BuildOrderKind,
+    pub object_id: u32,
+    /* e.g. `hpea` */
+    pub rawcode: String,
+    /* From the `Dictionary`, `None` when it doesn't know the rawcode */
+    pub name: Option<String>,
+    /* Shift was held so it was added to the end of the worker's orders */
+    pub queued: bool,
+    /* Where a structure was placed */
+    pub location: Option<(f32, f32)>,
+    /* Position in the queue of a cancelled unit, 0 being the one in progress */
+    pub slot_index: Option<u8>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct PlayerBuildOrder
+{
+    pub player_id: u8,
+    pub player_name: String,
+    pub entries: Vec<BuildOrderEntry>,
+}
+
+impl PlayerBuildOrder
+{
+    /// Only the entries of one kind, e.g. the structures without the units
+    pub fn entries_of(&self, kind: BuildOrderKind) -> Vec<&BuildOrderEntry>
+    {
+        self.entries.iter()
+            .filter(|entry| entry.kind == kind)
+            .collect()
+    }
+}
+
+/// The build order entry an action is, if it's one at all
+fn build_order_entry(time: u32, action: &Action, dictionary: &Dictionary) -> Option<BuildOrderEntry>
+{
+    let (kind, queued, location, slot_index) = match *action
+    {
+        Action::PointOrder { ref flags, x, y, .. } if flags.contains(&OrderType::Construct) =>
+            (BuildOrderKind::Construct, flags.contains(&OrderType::Queue), Some((x, y)), None),
+        Action::SelfOrder { ref flags, .. } | Action::PointOrder { ref flags, .. } | Action::ObjectOrder { ref flags, .. } if flags.contains(&OrderType::Train) =>
+            (BuildOrderKind::Train, flags.contains(&OrderType::Queue), None, None),
+        Action::CancelUnitInQueue { slot_index, .. } =>
+            (BuildOrderKind::Cancel, false, None, Some(slot_index)),
+        _ => return None,
+    };
+
+    /* Orders with the flags but without a rawcode are abilities, e.g. summons */
+    let object_id = action.object_id()?;
+    let rawcode = id_to_rawcode(object_id)?;
+
+    Some(
+        BuildOrderEntry {
+            time,
+            kind,
+            object_id,
+            rawcode,
+            name: dictionary.object_name(object_id).map(String::from),
+            queued,
+            location,
+            slot_index,
+        }
+    )
+}
+
+/// The build order of every player in the `GameHeader`, the replay saver first
+pub fn build_orders(replay: &Replay, dictionary: &Dictionary) -> Vec<PlayerBuildOrder>
+{
+    let timeline = replay.timeline();
+
+    player_records(replay).into_iter()
+        .map(|player|
+        {
+            PlayerBuildOrder {
+                player_id: player.player_id,
+                player_name: player.player_name.clone(),
+                entries: timeline.iter()
+                    .filter_map(|entry| match entry.event
+                    {
+                        TimelineEvent::Action { player_id, action } if player_id == player.player_id => build_order_entry(entry.time, action, dictionary),
+                        _ => None,
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,180 @@
+/*
+    Everything the replay says about each unit, building & item, keyed by its `GameObject`.
+
+    Replays only mention objects when a player does something with them so all of this is inferred:
+        owner       the first player to give the object an order, anyone can select (& so inspect) an enemy's units
+                    but only their owner (or an ally sharing control) can order them about
+        unit_type   from `SelectSubGroup`, which names the type of the unit it picks
+        orders      every order given while the object was selected, see `SelectionTracker`
+*/
+
+use std::collections::HashMap;
+
+use parser::{Replay, Action, GameObject, TimelineEvent};
+use super::selection::SelectionTracker;
+
+
+/// An order given to an object
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct EntityOrder<'a>
+{
+    /* Milliseconds of game time, see `Replay::timeline` */
+    pub time: u32,
+    pub player_id: u8,
+    /* One of 0x10 - 0x14 */
+    pub action: &'a Action,
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct Entity<'a>
+{
+    pub object: GameObject,
+    /* Milliseconds of game time of the first & last action that mentioned the object */
+    pub first_seen: u32,
+    pub last_seen: u32,
+    pub owner: Option<u8>,
+    /* Rawcode as an id, see `Dictionary::object_name` */
+    pub unit_type: Option<u32>,
+    pub orders: Vec<EntityOrder<'a>>,
+}
+
+impl<'a> Entity<'a>
+{
+    fn new(object: GameObject, time: u32) -> Entity<'a>
+    {
+        Entity {
+            object,
+            first_seen: time,
+            last_seen: time,
+            owner: None,
+            unit_type: None,
+            orders: Vec::new(),
+        }
+    }
+}
+
+/// Every object mentioned in a replay
+#[derive(Debug, PartialEq, Clone)]
+pub struct EntityRegistry<'a>
+{
+    entities: HashMap<GameObject, Entity<'a>>,
+}
+
+/// The objects an action mentions directly, not counting the selection it applies to
+fn mentioned_objects(action: &Action) -> Vec<GameObject>
+{
+    let objects = match *action
+    {
+        Action::ChangeSelection { ref targets, .. } | Action::AssignGroup { ref targets, .. } => targets.clone(),
+        Action::ObjectOrder { target, .. } => vec![target],
+        Action::DropOrGiveItem { receiver, item, .. } => vec![receiver, item],
+        Action::SelectSubGroup { target, .. } |
+        Action::TriggerSelectionEvent { target, .. } |
+        Action::SelectGroundItem { target, .. } |
+        Action::CancelHeroRevival { target } => vec![target],
+        _ => Vec::new(),
+    };
+
+    objects.into_iter()
+        .filter(|object| !object.is_none())
+        .collect()
+}
+
+impl<'a> EntityRegistry<'a>
+{
+    pub fn from_replay(replay: &'a Replay) -> EntityRegistry<'a>
+    {
+        let mut registry = EntityRegistry { entities: HashMap::new() };
+        let mut tracker = SelectionTracker::new();
+
+        for entry in replay.timeline()
+        {
+            let (player_id, action) = match entry.event
+            {
+                TimelineEvent::Action { player_id, action } => (player_id, action),
+                _ => continue,
+            };
+            let time = entry.time;
+
+            tracker.apply(player_id, action);
+
+            for object in mentioned_objects(action)
+            {
+                registry.see(object, time);
+            }
+
+            match *action
+            {
+                Action::SelectSubGroup { item_id, target } if !target.is_none() =>
+                {
+                    registry.see(target, time).unit_type = Some(item_id);
+                },
+                _ => {},
+            }
+
+            if action.order_id().is_some()
+            {
+                let selection = tracker.player(player_id)
+                    .map(|player| player.selection.clone())
+                    .unwrap_or_default();
+
+                for object in selection
+                {
+                    let entity = registry.see(object, time);
+                    entity.owner = entity.owner.or(Some(player_id));
+                    entity.orders.push(EntityOrder { time, player_id, action });
+                }
+            }
+        }
+
+        registry
+    }
+
+    /// Creates the entity the first time it's seen
+    fn see(&mut self, object: GameObject, time: u32) -> &mut Entity<'a>
+    {
+        let entity = self.entities.entry(object).or_insert_with(|| Entity::new(object, time));
+        entity.last_seen = time;
+
+        entity
+    }
+
+    pub fn get(&self, object: &GameObject) -> Option<&Entity<'a>>
+    {
+        self.entities.get(object)
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.entities.len()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.entities.is_empty()
+    }
+
+    /// In the order they were first seen
+    pub fn entities(&self) -> Vec<&Entity<'a>>
+    {
+        let mut entities = self.entities.values().collect::<Vec<_>>();
+        entities.sort_by_key(|entity| (entity.first_seen, entity.object.allocated_id, entity.object.counter_id));
+
+        entities
+    }
+
+    pub fn owned_by(&self, player_id: u8) -> Vec<&Entity<'a>>
+    {
+        self.entities().into_iter()
+            .filter(|entity| entity.owner == Some(player_id))
+            .collect()
+    }
+
+    /// e.g. every titan, `unit_type` is a rawcode as an id
+    pub fn of_type(&self, unit_type: u32) -> Vec<&Entity<'a>>
+    {
+        self.entities().into_iter()
+            .filter(|entity| entity.unit_type == Some(unit_type))
+            .collect()
+    }
+}
@@ -0,0 +1,295 @@
+/*
+    Where on the map players gave their orders, counted into a grid of cells.
+
+    Meant for e.g. where builders place their structures (to find their bases) or where titans move to.
+    Heatmaps can be built from one replay or added to from many as long as they were played on the same map.
+*/
+
+use byteorder::{BigEndian, WriteBytesExt};
+use libflate::zlib::Encoder;
+use crc::crc32;
+use serde_json;
+
+use std::fs::File;
+use std::io::Write;
+
+use parser::{Replay, Action, OrderType, TimelineEvent, GameSettings};
+use result::GameResult;
+use super::player_records;
+
+use ::errors::*;
+
+
+/// Size of a map cell in game coordinates
+const CELL_SIZE: f32 = 128.0;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+/// The area of the map in game coordinates
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone, new)]
+pub struct HeatmapBounds
+{
+    pub min_x: f32,
+    pub min_y: f32,
+    pub max_x: f32,
+    pub max_y: f32,
+}
+
+impl HeatmapBounds
+{
+    /// The playable area, maps are centered on 0, 0
+    pub fn from_settings(settings: &GameSettings) -> HeatmapBounds
+    {
+        let half_width = (settings.map_width as f32) * CELL_SIZE / 2.0;
+        let half_height = (settings.map_height as f32) * CELL_SIZE / 2.0;
+
+        HeatmapBounds::new(-half_width, -half_height, half_width, half_height)
+    }
+}
+
+/// Which orders are counted
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, new)]
+pub struct HeatmapFilter
+{
+    /* Only orders with the `Construct` flag, i.e. where structures were placed */
+    pub construct_only: bool,
+    /* `PlayerRecord.player_id`s, everyone when `None` */
+    pub player_ids: Option<Vec<u8>>,
+    /* Milliseconds of game time, see `Replay::timeline` */
+    pub start_time: u32,
+    pub end_time: Option<u32>,
+}
+
+/// Every order from every player
+impl Default for HeatmapFilter
+{
+    fn default() -> HeatmapFilter
+    {
+        HeatmapFilter {
+            construct_only: false,
+            player_ids: None,
+            start_time: 0,
+            end_time: None,
+        }
+    }
+}
+
+impl HeatmapFilter
+{
+    fn matches(&self, time: u32, player_id: u8, flags: &Vec<OrderType>) -> bool
+    {
+        (!self.construct_only || flags.contains(&OrderType::Construct))
+            && self.player_ids.as_ref().map(|player_ids| player_ids.contains(&player_id)).unwrap_or(true)
+            && time >= self.start_time
+            && self.end_time.map(|end_time| time <= end_time).unwrap_or(true)
+    }
+}
+
+/// The players of a replay that the map gave a role, e.g. "Titan", for `HeatmapFilter.player_ids`
+pub fn player_ids_with_role(replay: &Replay, result: &GameResult, role: &str) -> Vec<u8>
+{
+    player_ids_where(replay, result, |_, player_role| player_role == role)
+}
+
+/// The players of a replay on a team, e.g. "Builder", for `HeatmapFilter.player_ids`
+pub fn player_ids_in_team(replay: &Replay, result: &GameResult, team: &str) -> Vec<u8>
+{
+    player_ids_where(replay, result, |team_name, _| team_name == team)
+}
+
+/// Matched up by name since `GameResult` only has the `Player`s it was given
+fn player_ids_where<F>(replay: &Replay, result: &GameResult, predicate: F) -> Vec<u8>
+    where F: Fn(&str, &str) -> bool
+{
+    let names = result.teams.iter()
+        .flat_map(|team| team.players.iter().map(move |player_role| (team, player_role)))
+        .filter(|&(team, player_role)| predicate(&team.name, &player_role.role))
+        .map(|(_, player_role)| player_role.player.name.as_str())
+        .collect::<Vec<&str>>();
+
+    player_records(replay).into_iter()
+        .filter(|player| names.contains(&player.player_name.as_str()))
+        .map(|player| player.player_id)
+        .collect()
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Heatmap
+{
+    pub bounds: HeatmapBounds,
+    /* Number of cells */
+    pub width: u32,
+    pub height: u32,
+    /* `width * height` counts, row by row from the top (max y) so it reads like the map */
+    pub cells: Vec<u32>,
+    /* Orders that matched the filter but were outside of the bounds */
+    pub outside: u32,
+}
+
+impl Heatmap
+{
+    /// At least 1 by 1 cells, fails when `bounds` cover no area since points couldn't be placed in a cell
+    pub fn new(bounds: HeatmapBounds, width: u32, height: u32) -> Result<Heatmap>
+    {
+        /* Also false for NaN */
+        if !(bounds.min_x < bounds.max_x && bounds.min_y < bounds.max_y)
+        {
+            bail!(format!("{:?} is not an area", bounds));
+        }
+        let width = width.max(1);
+        let height = height.max(1);
+
+        Ok(Heatmap {
+            bounds,
+            width,
+            height,
+            cells: vec![0; (width * height) as usize],
+            outside: 0,
+        })
+    }
+
+    /// An empty heatmap over the map of `replay`
+    pub fn for_replay(replay: &Replay, width: u32, height: u32) -> Result<Heatmap>
+    {
+        let settings = replay.game_header.game_settings()?;
+
+        Heatmap::new(HeatmapBounds::from_settings(&settings), width, height)
+    }
+
+    /// `row` 0 is the top of the map
+    pub fn get(&self, column: u32, row: u32) -> Option<u32>
+    {
+        if column < self.width && row < self.height
+        {
+            self.cells.get((row * self.width + column) as usize).cloned()
+        } else
+        {
+            None
+        }
+    }
+
+    pub fn max(&self) -> u32
+    {
+        self.cells.iter().cloned().max().unwrap_or(0)
+    }
+
+    pub fn total(&self) -> u32
+    {
+        self.cells.iter().sum()
+    }
+
+    /// Counts a point, `false` when it's outside of the bounds
+    pub fn add_point(&mut self, x: f32, y: f32) -> bool
+    {
+        let bounds = self.bounds;
+        if !(x >= bounds.min_x && x <= bounds.max_x && y >= bounds.min_y && y <= bounds.max_y)
+        {
+            self.outside = self.outside + 1;
+            return false;
+        }
+
+        /* The max edges belong to the last column & row */
+        let column = (((x - bounds.min_x) / (bounds.max_x - bounds.min_x) * self.width as f32) as u32).min(self.width - 1);
+        let row = (((bounds.max_y - y) / (bounds.max_y - bounds.min_y) * self.height as f32) as u32).min(self.height - 1);
+        self.cells[(row * self.width + column) as usize] += 1;
+
+        true
+    }
+
+    /// Counts the location of every `PointOrder` & `ObjectOrder` in the replay that matches the filter
+    pub fn add_replay(&mut self, replay: &Replay, filter: &HeatmapFilter)
+    {
+        for entry in replay.timeline()
+        {
+            let (player_id, flags, x, y) = match entry.event
+            {
+                TimelineEvent::Action { player_id, action: &Action::PointOrder { ref flags, x, y, .. } } |
+                TimelineEvent::Action { player_id, action: &Action::ObjectOrder { ref flags, x, y, .. } } => (player_id, flags, x, y),
+                _ => continue,
+            };
+
+            if filter.matches(entry.time, player_id, flags)
+            {
+                self.add_point(x, y);
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String>
+    {
+        serde_json::to_string(self).chain_err(|| "Unable to serialize heatmap")
+    }
+
+    /// Renders every cell as a `cell_size` pixel square going from black through red & yellow to white at `max`
+    pub fn to_png(&self, cell_size: u32) -> Result<Vec<u8>>
+    {
+        let cell_size = cell_size.max(1);
+        let image_width = self.width * cell_size;
+        let image_height = self.height * cell_size;
+        let max = self.max().max(1) as f32;
+
+        /* Every row starts with its filter type, 0 is none */
+        let mut pixels = Vec::with_capacity(((image_width * 3 + 1) * image_height) as usize);
+        for row in 0..self.height
+        {
+            let mut line = vec![0u8];
+            for column in 0..self.width
+            {
+                let color = heat_color(self.get(column, row).unwrap_or(0) as f32 / max);
+                for _ in 0..cell_size
+                {
+                    line.extend_from_slice(&color);
+                }
+            }
+
+            for _ in 0..cell_size
+            {
+                pixels.extend_from_slice(&line);
+            }
+        }
+
+        let mut encoder = Encoder::new(Vec::new())?;
+        encoder.write_all(&pixels)?;
+        let compressed_pixels = encoder.finish().into_result()?;
+
+        let mut header = Vec::with_capacity(13);
+        header.write_u32::<BigEndian>(image_width)?;
+        header.write_u32::<BigEndian>(image_height)?;
+        /* 8 bits per channel, RGB, deflate, no filtering, not interlaced */
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        let mut png = PNG_SIGNATURE.to_vec();
+        write_png_chunk(&mut png, b"IHDR", &header)?;
+        write_png_chunk(&mut png, b"IDAT", &compressed_pixels)?;
+        write_png_chunk(&mut png, b"IEND", &[])?;
+
+        Ok(png)
+    }
+
+    pub fn save_png(&self, path: &str, cell_size: u32) -> Result<()>
+    {
+        let png = self.to_png(cell_size)?;
+        File::create(path)?.write_all(&png)?;
+
+        Ok(())
+    }
+}
+
+/// `heat` from 0 to 1
+fn heat_color(heat: f32) -> [u8; 3]
+{
+    let channel = |value: f32| (value.max(0.0).min(1.0) * 255.0) as u8;
+
+    [channel(heat * 3.0), channel(heat * 3.0 - 1.0), channel(heat * 3.0 - 2.0)]
+}
+
+/// Length, type, data then a crc32 of the type & data
+fn write_png_chunk(png: &mut Vec<u8>, chunk_type: &[u8; 4], data: &[u8]) -> Result<()>
+{
+    png.write_u32::<BigEndian>(data.len() as u32)?;
+    png.extend_from_slice(chunk_type);
+    png.extend_from_slice(data);
+    png.write_u32::<BigEndian>(crc32::checksum_ieee(&[&chunk_type[..], data].concat()))?;
+
+    Ok(())
+}
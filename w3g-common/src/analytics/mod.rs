@@ -0,0 +1,26 @@
+/*
+    Statistics worked out from parsed replays, everything here only reads a `Replay`.
+*/
+
+pub mod apm;
+pub mod build_order;
+pub mod entity;
+pub mod heatmap;
+pub mod selection;
+
+use parser::{Replay, PlayerRecord, TimelineEntry};
+
+
+/// The replay saver followed by everyone else in the `GameHeader`
+fn player_records(replay: &Replay) -> Vec<&PlayerRecord>
+{
+    Some(&replay.game_header.replay_saver).into_iter()
+        .chain(replay.game_header.players.iter())
+        .collect()
+}
+
+/// Game time of the last entry, i.e. how long the game lasted
+fn end_time(timeline: &Vec<TimelineEntry>) -> u32
+{
+    timeline.last().map(|entry| entry.time).unwrap_or(0)
+}
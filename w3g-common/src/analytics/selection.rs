@@ -0,0 +1,249 @@
+/*
+    Orders don't say which units they're for, they apply to whatever the player had selected at the time.
+    `SelectionTracker` follows the selection & control groups of every player through their actions:
+        ChangeSelection     adds to or removes from the selection
+        AssignGroup         sets a control group to the targets, which become the selection
+        SelectGroup         replaces the selection with a control group
+        SelectSubGroup      picks which type of unit in the selection has its command card shown (tab)
+        PreSubSelection     sent before the subgroup changes, the old subgroup no longer applies
+
+    Units that die aren't in the replay so they stay selected & in their groups until the player changes them.
+*/
+
+use std::collections::BTreeMap;
+
+use parser::{Replay, Action, GameObject, SelectionOperation, TimelineEvent};
+use super::player_records;
+
+
+/// The type of unit whose command card is shown, `target` is the first of them in the selection
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub struct SubGroup
+{
+    pub item_id: u32,
+    pub target: GameObject,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct ControlGroupUsage
+{
+    pub group_number: u8,
+    /* `AssignGroup` */
+    pub assigned: u32,
+    /* `SelectGroup` */
+    pub selected: u32,
+    /* Selected while it was already selected, i.e. double tapped to move the camera to it */
+    pub reselected: u32,
+    /* Units in the group when it was last assigned */
+    pub size: usize,
+}
+
+impl ControlGroupUsage
+{
+    fn new(group_number: u8) -> ControlGroupUsage
+    {
+        ControlGroupUsage {
+            group_number,
+            assigned: 0,
+            selected: 0,
+            reselected: 0,
+            size: 0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct PlayerSelection
+{
+    /* In the order they were selected */
+    pub selection: Vec<GameObject>,
+    pub subgroup: Option<SubGroup>,
+    pub groups: BTreeMap<u8, Vec<GameObject>>,
+    pub usage: BTreeMap<u8, ControlGroupUsage>,
+    /* The group that's selected, until the selection changes some other way */
+    selected_group: Option<u8>,
+}
+
+impl PlayerSelection
+{
+    fn new() -> PlayerSelection
+    {
+        PlayerSelection {
+            selection: Vec::new(),
+            subgroup: None,
+            groups: BTreeMap::new(),
+            usage: BTreeMap::new(),
+            selected_group: None,
+        }
+    }
+
+    fn apply(&mut self, action: &Action)
+    {
+        match *action
+        {
+            Action::ChangeSelection { select_mode: SelectionOperation::Add, ref targets } =>
+            {
+                for target in targets.iter()
+                {
+                    if !self.selection.contains(target)
+                    {
+                        self.selection.push(*target);
+                    }
+                }
+                self.selected_group = None;
+            },
+            Action::ChangeSelection { select_mode: SelectionOperation::Remove, ref targets } =>
+            {
+                self.selection.retain(|object| !targets.contains(object));
+                self.selected_group = None;
+            },
+            Action::AssignGroup { group_number, ref targets } =>
+            {
+                self.selection = targets.clone();
+                self.groups.insert(group_number, targets.clone());
+                self.selected_group = Some(group_number);
+
+                let usage = self.usage.entry(group_number).or_insert_with(|| ControlGroupUsage::new(group_number));
+                usage.assigned = usage.assigned + 1;
+                usage.size = targets.len();
+            },
+            Action::SelectGroup { group_number, .. } =>
+            {
+                self.selection = self.groups.get(&group_number).cloned().unwrap_or_default();
+                self.subgroup = None;
+
+                let usage = self.usage.entry(group_number).or_insert_with(|| ControlGroupUsage::new(group_number));
+                usage.selected = usage.selected + 1;
+                if self.selected_group == Some(group_number)
+                {
+                    usage.reselected = usage.reselected + 1;
+                }
+                self.selected_group = Some(group_number);
+            },
+            Action::SelectSubGroup { item_id, target } =>
+            {
+                self.subgroup = Some(SubGroup { item_id, target });
+            },
+            Action::PreSubSelection() =>
+            {
+                self.subgroup = None;
+            },
+            _ => {},
+        }
+    }
+}
+
+/// Follows the selection of every player as their actions are applied in order
+#[derive(Debug, PartialEq, Clone)]
+pub struct SelectionTracker
+{
+    players: BTreeMap<u8, PlayerSelection>,
+}
+
+impl SelectionTracker
+{
+    pub fn new() -> SelectionTracker
+    {
+        SelectionTracker {
+            players: BTreeMap::new(),
+        }
+    }
+
+    pub fn apply(&mut self, player_id: u8, action: &Action)
+    {
+        self.players.entry(player_id)
+            .or_insert_with(PlayerSelection::new)
+            .apply(action);
+    }
+
+    /// `None` until the player has made an action
+    pub fn player(&self, player_id: u8) -> Option<&PlayerSelection>
+    {
+        self.players.get(&player_id)
+    }
+}
+
+/// An order along with what the player had selected when they gave it
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct SelectedOrder<'a>
+{
+    /* Milliseconds of game time, see `Replay::timeline` */
+    pub time: u32,
+    pub player_id: u8,
+    /* One of 0x10 - 0x14 */
+    pub action: &'a Action,
+    pub selection: Vec<GameObject>,
+    pub subgroup: Option<SubGroup>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct PlayerControlGroups
+{
+    pub player_id: u8,
+    pub player_name: String,
+    /* Only the groups that were used, by group number */
+    pub groups: Vec<ControlGroupUsage>,
+}
+
+/// Every order in the replay with the selection it applied to
+pub fn selected_orders<'a>(replay: &'a Replay) -> Vec<SelectedOrder<'a>>
+{
+    let mut tracker = SelectionTracker::new();
+    let mut orders = Vec::new();
+
+    for entry in replay.timeline()
+    {
+        let (player_id, action) = match entry.event
+        {
+            TimelineEvent::Action { player_id, action } => (player_id, action),
+            _ => continue,
+        };
+
+        tracker.apply(player_id, action);
+
+        if action.order_id().is_some()
+        {
+            let (selection, subgroup) = match tracker.player(player_id)
+            {
+                Some(player) => (player.selection.clone(), player.subgroup),
+                None => (Vec::new(), None),
+            };
+
+            orders.push( SelectedOrder {
+                time: entry.time,
+                player_id,
+                action,
+                selection,
+                subgroup,
+            });
+        }
+    }
+
+    orders
+}
+
+/// How every player in the `GameHeader` used their control groups, the replay saver first
+pub fn control_group_usage(replay: &Replay) -> Vec<PlayerControlGroups>
+{
+    let mut tracker = SelectionTracker::new();
+    for entry in replay.timeline()
+    {
+        if let TimelineEvent::Action { player_id, action } = entry.event
+        {
+            tracker.apply(player_id, action);
+        }
+    }
+
+    player_records(replay).into_iter()
+        .map(|player|
+        {
+            PlayerControlGroups {
+                player_id: player.player_id,
+                player_name: player.player_name.clone(),
+                groups: tracker.player(player.player_id)
+                    .map(|selection| selection.usage.values().cloned().collect())
+                    .unwrap_or_default(),
+            }
+        })
+        .collect()
+}
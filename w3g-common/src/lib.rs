@@ -21,7 +21,11 @@ extern crate bbt;
 
 extern crate byteorder;
 extern crate libflate;
+extern crate crc;
+extern crate rayon;
+extern crate encoding_rs;
 extern crate serde; 
+extern crate serde_json;
 extern crate rmp_serde;
 
 extern crate kafka;
@@ -33,6 +37,8 @@ pub mod parser;
 pub mod pubsub;
 pub mod api;
 pub mod rating;
+pub mod result;
+pub mod analytics;
 
 /*
     Common error_chain for all of lib to use so the ? operator passes things around real well.
@@ -59,5 +65,17 @@ pub mod errors {
             Reqwest(::reqwest::Error);
             RocketJson(::rocket_contrib::SerdeError);
         }
+
+        errors {
+            /* e.g. 5 for a `GameSpeed`, turned into a `ParseError::InvalidValue` once the parser knows where it was */
+            InvalidValue(type_name: String, value: u64) {
+                description("invalid value")
+                display("{} is not a {}", value, type_name)
+            }
+            Parse(error: ::parser::ParseError) {
+                description("unable to parse replay")
+                display("{}", error)
+            }
+        }
     }
 }
\ No newline at end of file
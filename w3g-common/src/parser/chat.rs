@@ -0,0 +1,91 @@
+use super::parser::{Replay, ReplayBlock, LOBBY_CHAT_FLAGS};
+use super::timeline::TimelineEvent;
+
+
+/// Who a `ChatMessage` was sent to, decoded from `ReplayBlock::PlayerChat.flags` & `chat_mode`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum ChatChannel
+{
+    /* chat_mode 0x00 */
+    All,
+    /* chat_mode 0x01 */
+    Allies,
+    /* chat_mode 0x02, observers & referees */
+    Observers,
+    /* chat_mode 0x03 + slot, whispered to whoever is in `GameRecord.slot_records[slot]` */
+    Private {
+        slot: u8,
+    },
+    /* Sent before the game started */
+    Lobby,
+}
+
+impl ChatChannel
+{
+    pub fn from_chat(flags: u8, chat_mode: u32) -> ChatChannel
+    {
+        if flags == LOBBY_CHAT_FLAGS
+        {
+            return ChatChannel::Lobby;
+        }
+
+        match chat_mode
+        {
+            0x00 => ChatChannel::All,
+            0x01 => ChatChannel::Allies,
+            0x02 => ChatChannel::Observers,
+            _ => ChatChannel::Private { slot: (chat_mode - 0x03) as u8 },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct ChatMessage
+{
+    /* Milliseconds of game time, see `Replay::timeline` */
+    pub time: u32,
+    pub player_id: u8,
+    /* `None` when the player isn't in the `GameHeader` */
+    pub sender: Option<String>,
+    pub channel: ChatChannel,
+    /* Name of who a `ChatChannel::Private` message was sent to */
+    pub recipient: Option<String>,
+    pub message: String,
+}
+
+impl Replay
+{
+    /// Every `ReplayBlock::PlayerChat` in order with the names of who sent (and received) it
+    pub fn chat_log(&self) -> Vec<ChatMessage>
+    {
+        let player_name = |player_id: u8| self.game_header.player(player_id).map(|player| player.player_name.clone());
+
+        self.timeline().into_iter()
+            .filter_map(|entry| match entry.event
+            {
+                TimelineEvent::Block(&ReplayBlock::PlayerChat { player_id, flags, chat_mode, ref message, .. }) =>
+                {
+                    let channel = ChatChannel::from_chat(flags, chat_mode);
+                    let recipient = match channel
+                    {
+                        ChatChannel::Private { slot } => self.game_header.game_record.slot_records.get(slot as usize)
+                            .and_then(|slot| player_name(slot.player_id)),
+                        _ => None,
+                    };
+
+                    Some(
+                        ChatMessage {
+                            time: entry.time,
+                            player_id,
+                            sender: player_name(player_id),
+                            channel,
+                            recipient,
+                            message: message.clone(),
+                        }
+                    )
+                },
+                _ => None,
+            })
+            .collect()
+    }
+}
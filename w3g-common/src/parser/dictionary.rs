@@ -0,0 +1,234 @@
+/*
+    Names for the order ids & object rawcodes that actions carry.
+
+    Orders that aren't abilities (train, build, research, ...) use the rawcode of what's being made as their order id,
+    e.g. 0x68706561 is 'hpea' a Peasant.
+*/
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use super::parser::Action;
+
+use ::errors::*;
+
+
+/// Order ids that are the same in every map, see `OrderId` in common.j
+const ORDERS: &'static [(u32, &'static str)] = &[
+    (0x000D_0003, "smart"),
+    (0x000D_0004, "stop"),
+    (0x000D_0008, "cancel"),
+    (0x000D_000C, "setrally"),
+    (0x000D_000D, "getitem"),
+    (0x000D_000F, "attack"),
+    (0x000D_0010, "attackground"),
+    (0x000D_0011, "attackonce"),
+    (0x000D_0012, "move"),
+    (0x000D_0014, "AImove"),
+    (0x000D_0016, "patrol"),
+    (0x000D_0019, "holdposition"),
+    (0x000D_001A, "build"),
+    (0x000D_001B, "humanbuild"),
+    (0x000D_001C, "orcbuild"),
+    (0x000D_001D, "nightelfbuild"),
+    (0x000D_001E, "undeadbuild"),
+    (0x000D_001F, "resumebuild"),
+    (0x000D_0021, "dropitem"),
+    (0x000D_0022, "moveslot1"),
+    (0x000D_0023, "moveslot2"),
+    (0x000D_0024, "moveslot3"),
+    (0x000D_0025, "moveslot4"),
+    (0x000D_0026, "moveslot5"),
+    (0x000D_0027, "moveslot6"),
+    (0x000D_0028, "useslot1"),
+    (0x000D_0029, "useslot2"),
+    (0x000D_002A, "useslot3"),
+    (0x000D_002B, "useslot4"),
+    (0x000D_002C, "useslot5"),
+    (0x000D_002D, "useslot6"),
+    (0x000D_0031, "resumeharvesting"),
+    (0x000D_0032, "harvest"),
+    (0x000D_0034, "returnresources"),
+    (0x000D_0035, "autoharvestgold"),
+    (0x000D_0036, "autoharvestlumber"),
+    (0x000D_0037, "neutraldetectaoe"),
+    (0x000D_0038, "repair"),
+    (0x000D_0039, "repairon"),
+    (0x000D_003A, "repairoff"),
+    (0x000D_003B, "revive"),
+];
+
+/// Units, heroes & buildings of the 4 melee races
+const OBJECTS: &'static [(&'static str, &'static str)] = &[
+    /* Human */
+    ("hpea", "Peasant"), ("hfoo", "Footman"), ("hkni", "Knight"), ("hrif", "Rifleman"), ("hmtm", "Mortar Team"),
+    ("hgyr", "Flying Machine"), ("hgry", "Gryphon Rider"), ("hmpr", "Priest"), ("hsor", "Sorceress"), ("hspt", "Spell Breaker"),
+    ("hdhw", "Dragonhawk Rider"), ("hmtt", "Siege Engine"),
+    ("hhou", "Farm"), ("htow", "Town Hall"), ("hkee", "Keep"), ("hcas", "Castle"), ("hbar", "Barracks"),
+    ("halt", "Altar of Kings"), ("hlum", "Lumber Mill"), ("hbla", "Blacksmith"), ("harm", "Workshop"), ("hars", "Arcane Sanctum"),
+    ("hgra", "Gryphon Aviary"), ("hwtw", "Scout Tower"), ("hgtw", "Guard Tower"), ("hctw", "Cannon Tower"), ("hatw", "Arcane Tower"),
+    ("hvlt", "Arcane Vault"),
+    ("Hpal", "Paladin"), ("Hamg", "Archmage"), ("Hmkg", "Mountain King"), ("Hblm", "Blood Mage"),
+    /* Orc */
+    ("opeo", "Peon"), ("ogru", "Grunt"), ("ohun", "Headhunter"), ("ocat", "Demolisher"), ("oshm", "Shaman"),
+    ("odoc", "Witch Doctor"), ("ospw", "Spirit Walker"), ("orai", "Raider"), ("okod", "Kodo Beast"), ("owyv", "Wind Rider"),
+    ("otbr", "Troll Batrider"), ("otau", "Tauren"),
+    ("ogre", "Great Hall"), ("ostr", "Stronghold"), ("ofrt", "Fortress"), ("obar", "Barracks"), ("oalt", "Altar of Storms"),
+    ("ofor", "War Mill"), ("osld", "Spirit Lodge"), ("obea", "Beastiary"), ("otto", "Tauren Totem"), ("otrb", "Orc Burrow"),
+    ("owtw", "Watch Tower"), ("ovln", "Voodoo Lounge"),
+    ("Obla", "Blademaster"), ("Ofar", "Far Seer"), ("Otch", "Tauren Chieftain"), ("Oshd", "Shadow Hunter"),
+    /* Undead */
+    ("uaco", "Acolyte"), ("ugho", "Ghoul"), ("ucry", "Crypt Fiend"), ("ugar", "Gargoyle"), ("uabo", "Abomination"),
+    ("umtw", "Meat Wagon"), ("unec", "Necromancer"), ("uban", "Banshee"), ("ufro", "Frost Wyrm"), ("uobs", "Obsidian Statue"),
+    ("ushd", "Shade"),
+    ("unpl", "Necropolis"), ("unp1", "Halls of the Dead"), ("unp2", "Black Citadel"), ("uzig", "Ziggurat"), ("uzg1", "Spirit Tower"),
+    ("uzg2", "Nerubian Tower"), ("uaod", "Altar of Darkness"), ("usep", "Crypt"), ("ugrv", "Graveyard"), ("utom", "Tomb of Relics"),
+    ("uslh", "Slaughterhouse"), ("utod", "Temple of the Damned"), ("ubon", "Boneyard"), ("ugol", "Haunted Gold Mine"), ("usap", "Sacrificial Pit"),
+    ("Udea", "Death Knight"), ("Ulic", "Lich"), ("Udre", "Dreadlord"), ("Ucrl", "Crypt Lord"),
+    /* Night Elf */
+    ("ewsp", "Wisp"), ("earc", "Archer"), ("esen", "Huntress"), ("ebal", "Glaive Thrower"), ("edry", "Dryad"),
+    ("edoc", "Druid of the Claw"), ("emtg", "Mountain Giant"), ("ehip", "Hippogryph"), ("ehpr", "Hippogryph Rider"), ("edot", "Druid of the Talon"),
+    ("efdr", "Faerie Dragon"), ("echm", "Chimaera"),
+    ("etol", "Tree of Life"), ("etoa", "Tree of Ages"), ("etoe", "Tree of Eternity"), ("emow", "Moon Well"), ("eate", "Altar of Elders"),
+    ("eaom", "Ancient of War"), ("eaoe", "Ancient of Lore"), ("eaow", "Ancient of Wind"), ("etrp", "Ancient Protector"), ("edob", "Hunter's Hall"),
+    ("edos", "Chimaera Roost"), ("eden", "Ancient of Wonders"),
+    ("Edem", "Demon Hunter"), ("Ekee", "Keeper of the Grove"), ("Emoo", "Priestess of the Moon"), ("Ewar", "Warden"),
+];
+
+/// Looks up names for order ids and object rawcodes
+#[derive(Debug, PartialEq, Clone)]
+pub struct Dictionary
+{
+    orders: HashMap<u32, String>,
+    /* Keyed by the rawcode as an id, see `rawcode_to_id` */
+    objects: HashMap<u32, String>,
+}
+
+/// Only the built in melee orders & objects
+impl Default for Dictionary
+{
+    fn default() -> Dictionary
+    {
+        Dictionary
+        {
+            orders: ORDERS.iter().map(|&(id, name)| (id, String::from(name))).collect(),
+            objects: OBJECTS.iter().map(|&(rawcode, name)| (rawcode_to_id(rawcode).unwrap(), String::from(name))).collect(),
+        }
+    }
+}
+
+impl Dictionary
+{
+    /// Adds (or overrides) a single object, `rawcode` must be 4 characters e.g. `h000`
+    pub fn add_object(&mut self, rawcode: &str, name: &str) -> Result<()>
+    {
+        let id = rawcode_to_id(rawcode).ok_or(format!("{} is not a rawcode", rawcode))?;
+        self.objects.insert(id, String::from(name));
+
+        Ok(())
+    }
+
+    /// Adds the objects of a map from a file with a `rawcode=Name` per line, blank lines & lines starting with `#` are skipped
+    pub fn load_objects(&mut self, path: &str) -> Result<()>
+    {
+        let file = BufReader::new(File::open(path)?);
+        for line in file.lines()
+        {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#')
+            {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let rawcode = parts.next().unwrap_or("").trim();
+            let name = parts.next().ok_or(format!("{} is not in the form rawcode=Name", line))?.trim();
+            self.add_object(rawcode, name)?;
+        }
+
+        Ok(())
+    }
+
+    /// Name of an order, falling back to the object being made for orders that are rawcodes
+    pub fn order_name(&self, order_id: u32) -> Option<&str>
+    {
+        self.orders.get(&order_id)
+            .or_else(|| self.objects.get(&order_id))
+            .map(|name| name.as_str())
+    }
+
+    pub fn object_name(&self, id: u32) -> Option<&str>
+    {
+        self.objects.get(&id).map(|name| name.as_str())
+    }
+}
+
+/// e.g. 0x68706561 to `hpea`, `None` when the id isn't 4 letters or digits
+pub fn id_to_rawcode(id: u32) -> Option<String>
+{
+    let bytes = [(id >> 24) as u8, (id >> 16) as u8, (id >> 8) as u8, id as u8];
+    if bytes.iter().all(|byte| byte.is_ascii_alphanumeric())
+    {
+        Some(bytes.iter().map(|byte| *byte as char).collect())
+    } else
+    {
+        None
+    }
+}
+
+/// e.g. `hpea` to 0x68706561
+pub fn rawcode_to_id(rawcode: &str) -> Option<u32>
+{
+    let bytes = rawcode.as_bytes();
+    if bytes.len() != 4
+    {
+        return None;
+    }
+
+    Some(bytes.iter().fold(0, |id, byte| (id << 8) | *byte as u32))
+}
+
+impl Action
+{
+    /// The order id of 0x10 - 0x14
+    pub fn order_id(&self) -> Option<u32>
+    {
+        match *self
+        {
+            Action::SelfOrder { order_id, .. } |
+            Action::PointOrder { order_id, .. } |
+            Action::ObjectOrder { order_id, .. } |
+            Action::DropOrGiveItem { order_id, .. } |
+            Action::FogObjectOrder { order_id, .. } => Some(order_id),
+            _ => None,
+        }
+    }
+
+    pub fn order_name<'a>(&self, dictionary: &'a Dictionary) -> Option<&'a str>
+    {
+        self.order_id().and_then(|order_id| dictionary.order_name(order_id))
+    }
+
+    /// The rawcode of the object an action is about, e.g. the unit being trained by an order or cancelled by `CancelUnitInQueue`
+    pub fn object_id(&self) -> Option<u32>
+    {
+        match *self
+        {
+            Action::SelectSubGroup { item_id, .. } => Some(item_id),
+            Action::CancelUnitInQueue { unit_id, .. } => Some(unit_id),
+            _ => self.order_id().filter(|order_id| id_to_rawcode(*order_id).is_some()),
+        }
+    }
+
+    pub fn object_rawcode(&self) -> Option<String>
+    {
+        self.object_id().and_then(id_to_rawcode)
+    }
+
+    pub fn object_name<'a>(&self, dictionary: &'a Dictionary) -> Option<&'a str>
+    {
+        self.object_id().and_then(|id| dictionary.object_name(id))
+    }
+}
@@ -0,0 +1,163 @@
+/*
+    Reforged writes UTF-8 but older clients wrote names, game names & chat in whatever codepage Windows was set to,
+    e.g. CP949 for Korean or CP1251 for Cyrillic. Valid UTF-8 is always taken as is, `StringEncoding` decides what happens to everything else.
+*/
+
+use encoding_rs::{Encoding, EUC_KR, GBK, BIG5, SHIFT_JIS, WINDOWS_1250, WINDOWS_1251, WINDOWS_1252};
+
+use std::borrow::Cow;
+use std::str;
+
+
+/// How strings that aren't valid UTF-8 are decoded, see `ParseOptions.string_encoding`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum StringEncoding
+{
+    /* Fail the whole replay */
+    Strict,
+    /* Invalid bytes become U+FFFD */
+    Lossy,
+    /* The codepage of `GameHeader.language_id`, lossy when it isn't a language we know */
+    Guess,
+    /* Always this codepage */
+    Codepage(Codepage),
+}
+
+impl Default for StringEncoding
+{
+    fn default() -> StringEncoding
+    {
+        StringEncoding::Strict
+    }
+}
+
+/// The legacy Windows codepages Warcraft III was played with
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum Codepage
+{
+    /* CP949 */
+    Korean,
+    /* CP936 */
+    SimplifiedChinese,
+    /* CP950 */
+    TraditionalChinese,
+    /* CP932 */
+    Japanese,
+    /* CP1250 */
+    CentralEuropean,
+    /* CP1251 */
+    Cyrillic,
+    /* CP1252 */
+    WesternEuropean,
+}
+
+const CODEPAGES: [Codepage; 7] = [
+    Codepage::Korean,
+    Codepage::SimplifiedChinese,
+    Codepage::TraditionalChinese,
+    Codepage::Japanese,
+    Codepage::CentralEuropean,
+    Codepage::Cyrillic,
+    Codepage::WesternEuropean,
+];
+
+impl Codepage
+{
+    /// `language_id` is a Windows LCID, but plenty of replays store something else there (e.g. 0x12F8B0) which gives `None`
+    pub fn from_language_id(language_id: u32) -> Option<Codepage>
+    {
+        if language_id > 0xFFFF
+        {
+            return None;
+        }
+
+        let primary_language = language_id & 0x3FF;
+        let sub_language = language_id >> 10;
+
+        match primary_language
+        {
+            0x12 => Some(Codepage::Korean),
+            /* Taiwan & Hong Kong */
+            0x04 if sub_language == 0x01 || sub_language == 0x03 => Some(Codepage::TraditionalChinese),
+            0x04 => Some(Codepage::SimplifiedChinese),
+            0x11 => Some(Codepage::Japanese),
+            /* Czech, Hungarian, Polish, Romanian, Slovak, Slovenian */
+            0x05 | 0x0E | 0x15 | 0x18 | 0x1B | 0x24 => Some(Codepage::CentralEuropean),
+            /* Bulgarian, Russian, Ukrainian, Belarusian */
+            0x02 | 0x19 | 0x22 | 0x23 => Some(Codepage::Cyrillic),
+            /* Danish, German, English, Spanish, Finnish, French, Italian, Dutch, Norwegian, Portuguese, Swedish */
+            0x06 | 0x07 | 0x09 | 0x0A | 0x0B | 0x0C | 0x10 | 0x13 | 0x14 | 0x16 | 0x1D => Some(Codepage::WesternEuropean),
+            _ => None,
+        }
+    }
+
+    fn encoding(&self) -> &'static Encoding
+    {
+        match *self
+        {
+            /* encoding_rs' EUC-KR is the CP949 superset */
+            Codepage::Korean => EUC_KR,
+            Codepage::SimplifiedChinese => GBK,
+            Codepage::TraditionalChinese => BIG5,
+            Codepage::Japanese => SHIFT_JIS,
+            Codepage::CentralEuropean => WINDOWS_1250,
+            Codepage::Cyrillic => WINDOWS_1251,
+            Codepage::WesternEuropean => WINDOWS_1252,
+        }
+    }
+
+    /// Bytes the codepage doesn't have become U+FFFD
+    pub fn decode(&self, bytes: &[u8]) -> String
+    {
+        self.encoding().decode_without_bom_handling(bytes).0.into_owned()
+    }
+}
+
+/// Decodes bytes that aren't UTF-8, `None` when `encoding` is `Strict`
+fn decode_legacy(bytes: &[u8], encoding: StringEncoding, language_id: Option<u32>) -> Option<String>
+{
+    match encoding
+    {
+        StringEncoding::Strict => None,
+        StringEncoding::Lossy => Some(String::from_utf8_lossy(bytes).into_owned()),
+        StringEncoding::Guess =>
+        {
+            match language_id.and_then(Codepage::from_language_id)
+            {
+                Some(codepage) => Some(codepage.decode(bytes)),
+                None => Some(String::from_utf8_lossy(bytes).into_owned()),
+            }
+        },
+        StringEncoding::Codepage(codepage) => Some(codepage.decode(bytes)),
+    }
+}
+
+/// The string along with the original bytes when they weren't valid UTF-8, `None` when `encoding` is `Strict` and they weren't
+pub fn decode_string(bytes: Vec<u8>, encoding: StringEncoding, language_id: Option<u32>) -> Option<(String, Option<Vec<u8>>)>
+{
+    match String::from_utf8(bytes)
+    {
+        Ok(string) => Some((string, None)),
+        Err(error) =>
+        {
+            let bytes = error.into_bytes();
+            decode_legacy(&bytes, encoding, language_id).map(|string| (string, Some(bytes)))
+        },
+    }
+}
+
+/// Like `decode_string` but borrows `bytes` when they're valid UTF-8
+pub fn decode_str<'a>(bytes: &'a [u8], encoding: StringEncoding, language_id: Option<u32>) -> Option<Cow<'a, str>>
+{
+    match str::from_utf8(bytes)
+    {
+        Ok(string) => Some(Cow::Borrowed(string)),
+        Err(_) => decode_legacy(bytes, encoding, language_id).map(Cow::Owned),
+    }
+}
+
+/// Whether `bytes` are what `value` was decoded from by any `StringEncoding`, i.e. the string hasn't been changed since
+pub fn decodes_to(bytes: &[u8], value: &str) -> bool
+{
+    String::from_utf8_lossy(bytes) == value || CODEPAGES.iter().any(|codepage| codepage.decode(bytes) == value)
+}
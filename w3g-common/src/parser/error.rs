@@ -0,0 +1,103 @@
+use std::fmt;
+
+use super::parser::ParseWarning;
+
+
+/// Where in the decompressed data the parser was when it failed
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+pub struct ParseContext
+{
+    /* Bytes of decompressed data consumed so far, 0 is the start of the `GameHeader` */
+    pub offset: u64,
+    /* None while the `GameHeader` is being parsed */
+    pub block_id: Option<u8>,
+    /* Set for `Command`s and `ReplayBlock::PlayerChat` */
+    pub player_id: Option<u8>,
+    pub action_id: Option<u8>,
+}
+
+/// Why a replay couldn't be parsed, carried by `ErrorKind::Parse`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub enum ParseError
+{
+    /* e.g. 5 for a `GameSpeed` */
+    InvalidValue {
+        context: ParseContext,
+        type_name: String,
+        value: u64,
+    },
+    /* `context.action_id` isn't known so the rest of the command can't be read */
+    UnknownAction {
+        context: ParseContext,
+    },
+    UnterminatedString {
+        context: ParseContext,
+    },
+    InvalidUtf8 {
+        context: ParseContext,
+    },
+    /* The data ran out part way through something */
+    UnexpectedEnd {
+        context: ParseContext,
+    },
+    /* Only happens with `ParseMode::Strict`, `ParseMode::Lenient` would have kept `warning` and carried on */
+    Rejected {
+        context: ParseContext,
+        warning: ParseWarning,
+    },
+    Other {
+        context: ParseContext,
+        message: String,
+    },
+}
+
+impl ParseError
+{
+    pub fn context(&self) -> &ParseContext
+    {
+        match *self
+        {
+            ParseError::InvalidValue { ref context, .. } => context,
+            ParseError::UnknownAction { ref context } => context,
+            ParseError::UnterminatedString { ref context } => context,
+            ParseError::InvalidUtf8 { ref context } => context,
+            ParseError::UnexpectedEnd { ref context } => context,
+            ParseError::Rejected { ref context, .. } => context,
+            ParseError::Other { ref context, .. } => context,
+        }
+    }
+}
+
+impl fmt::Display for ParseError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        match *self
+        {
+            ParseError::InvalidValue { ref type_name, value, .. } => write!(f, "{} is not a {}", value, type_name)?,
+            ParseError::UnknownAction { .. } => write!(f, "Unknown action")?,
+            ParseError::UnterminatedString { .. } => write!(f, "String did not end in \\0")?,
+            ParseError::InvalidUtf8 { .. } => write!(f, "String is not valid UTF-8")?,
+            ParseError::UnexpectedEnd { .. } => write!(f, "Ran out of data")?,
+            ParseError::Rejected { ref warning, .. } => write!(f, "{:?}", warning)?,
+            ParseError::Other { ref message, .. } => write!(f, "{}", message)?,
+        };
+
+        let context = self.context();
+        write!(f, " at offset {}", context.offset)?;
+        if let Some(block_id) = context.block_id
+        {
+            write!(f, ", block_id: 0x{:02X}", block_id)?;
+        }
+        if let Some(player_id) = context.player_id
+        {
+            write!(f, ", player_id: {}", player_id)?;
+        }
+        if let Some(action_id) = context.action_id
+        {
+            write!(f, ", action_id: 0x{:02X}", action_id)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+/*
+    Maps persist data (e.g. who won) through the game cache, every write is synced to the other players as a `SyncStored` or `SyncEmpty` action.
+    Each type has its own storage so an integer & a string can share a file/group/key.
+*/
+
+use std::collections::BTreeMap;
+
+use super::parser::{Replay, Action};
+use super::timeline::TimelineEvent;
+
+
+#[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub enum GameCacheType
+{
+    Integer,
+    Float,
+    Boolean,
+    String,
+    Unit,
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub enum GameCacheValue<'a>
+{
+    Integer(i32),
+    Float(f32),
+    Boolean(bool),
+    String(&'a str),
+    /* Always an `Action::SyncStoredUnit` */
+    Unit(&'a Action),
+}
+
+impl<'a> GameCacheValue<'a>
+{
+    pub fn cache_type(&self) -> GameCacheType
+    {
+        match *self
+        {
+            GameCacheValue::Integer(_) => GameCacheType::Integer,
+            GameCacheValue::Float(_) => GameCacheType::Float,
+            GameCacheValue::Boolean(_) => GameCacheType::Boolean,
+            GameCacheValue::String(_) => GameCacheType::String,
+            GameCacheValue::Unit(_) => GameCacheType::Unit,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i32>
+    {
+        match *self
+        {
+            GameCacheValue::Integer(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&'a str>
+    {
+        match *self
+        {
+            GameCacheValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub enum GameCacheChange<'a>
+{
+    /* SyncStored* */
+    Store(GameCacheValue<'a>),
+    /* SyncEmpty* */
+    Empty(GameCacheType),
+}
+
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct GameCacheEntry<'a>
+{
+    /* Milliseconds of game time, see `Replay::timeline` */
+    pub time: u32,
+    pub player_id: u8,
+    pub file: &'a str,
+    pub group: &'a str,
+    pub key: &'a str,
+    pub change: GameCacheChange<'a>,
+}
+
+/// Where a value lives in the game cache
+#[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub struct GameCacheKey<'a>
+{
+    pub file: &'a str,
+    pub group: &'a str,
+    pub key: &'a str,
+    pub cache_type: GameCacheType,
+}
+
+impl<'a> GameCacheEntry<'a>
+{
+    pub fn cache_key(&self) -> GameCacheKey<'a>
+    {
+        let cache_type = match self.change
+        {
+            GameCacheChange::Store(ref value) => value.cache_type(),
+            GameCacheChange::Empty(cache_type) => cache_type,
+        };
+
+        GameCacheKey {
+            file: self.file,
+            group: self.group,
+            key: self.key,
+            cache_type,
+        }
+    }
+
+    fn from_action(time: u32, player_id: u8, action: &'a Action) -> Option<GameCacheEntry<'a>>
+    {
+        let (file, group, key, change) = match *action
+        {
+            Action::SyncStoredInteger { ref file, ref group, ref key, value } => (file, group, key, GameCacheChange::Store(GameCacheValue::Integer(value))),
+            Action::SyncStoredFloat { ref file, ref group, ref key, value } => (file, group, key, GameCacheChange::Store(GameCacheValue::Float(value))),
+            Action::SyncStoredBoolean { ref file, ref group, ref key, value } => (file, group, key, GameCacheChange::Store(GameCacheValue::Boolean(value != 0))),
+            Action::SyncStoredString { ref file, ref group, ref key, ref value } => (file, group, key, GameCacheChange::Store(GameCacheValue::String(value))),
+            Action::SyncStoredUnit { ref file, ref group, ref key, .. } => (file, group, key, GameCacheChange::Store(GameCacheValue::Unit(action))),
+            Action::SyncEmptyInteger { ref file, ref group, ref key } => (file, group, key, GameCacheChange::Empty(GameCacheType::Integer)),
+            Action::SyncEmptyFloat { ref file, ref group, ref key } => (file, group, key, GameCacheChange::Empty(GameCacheType::Float)),
+            Action::SyncEmptyBoolean { ref file, ref group, ref key } => (file, group, key, GameCacheChange::Empty(GameCacheType::Boolean)),
+            Action::SyncEmptyString { ref file, ref group, ref key } => (file, group, key, GameCacheChange::Empty(GameCacheType::String)),
+            Action::SyncEmptyUnit { ref file, ref group, ref key } => (file, group, key, GameCacheChange::Empty(GameCacheType::Unit)),
+            _ => return None,
+        };
+
+        Some(
+            GameCacheEntry {
+                time,
+                player_id,
+                file,
+                group,
+                key,
+                change,
+            }
+        )
+    }
+}
+
+impl Replay
+{
+    /// Every game cache write & delete in the order they happened
+    pub fn game_cache<'a>(&'a self) -> Vec<GameCacheEntry<'a>>
+    {
+        self.timeline().into_iter()
+            .filter_map(|entry| match entry.event
+            {
+                TimelineEvent::Action { player_id, action } => GameCacheEntry::from_action(entry.time, player_id, action),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// What the game cache held when the replay ended
+    pub fn game_cache_state<'a>(&'a self) -> BTreeMap<GameCacheKey<'a>, GameCacheValue<'a>>
+    {
+        let mut state = BTreeMap::new();
+
+        for entry in self.game_cache()
+        {
+            let cache_key = entry.cache_key();
+            match entry.change
+            {
+                GameCacheChange::Store(value) => { state.insert(cache_key, value); },
+                GameCacheChange::Empty(_) => { state.remove(&cache_key); },
+            }
+        }
+
+        state
+    }
+}
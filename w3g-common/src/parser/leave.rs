@@ -0,0 +1,122 @@
+use super::parser::{Replay, ReplayBlock};
+use super::timeline::TimelineEvent;
+
+
+/// `ReplayBlock::LeaveGame.reason`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum LeaveReason
+{
+    /* 0x01 */
+    ConnectionClosedByRemote,
+    /* 0x0C, usually the replay saver */
+    ConnectionClosedByLocal,
+    /* 0x0E, rare and otherwise looks like `ConnectionClosedByRemote` */
+    Disconnected,
+    Unknown(u32),
+}
+
+impl LeaveReason
+{
+    pub fn from_u32(reason: u32) -> LeaveReason
+    {
+        match reason
+        {
+            0x01 => LeaveReason::ConnectionClosedByRemote,
+            0x0C => LeaveReason::ConnectionClosedByLocal,
+            0x0E => LeaveReason::Disconnected,
+            _ => LeaveReason::Unknown(reason),
+        }
+    }
+}
+
+/// `ReplayBlock::LeaveGame.result`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum LeaveResult
+{
+    /* 0x01, also used when the replay saver is an observer */
+    Disconnected,
+    /* 0x07 */
+    Left,
+    /* 0x08, completely erased */
+    Lost,
+    /* 0x09 */
+    Won,
+    /* 0x0A */
+    Draw,
+    /* 0x0B */
+    ObserverLeft,
+    Unknown(u32),
+}
+
+impl LeaveResult
+{
+    pub fn from_u32(result: u32) -> LeaveResult
+    {
+        match result
+        {
+            0x01 => LeaveResult::Disconnected,
+            0x07 => LeaveResult::Left,
+            0x08 => LeaveResult::Lost,
+            0x09 => LeaveResult::Won,
+            0x0A => LeaveResult::Draw,
+            0x0B => LeaveResult::ObserverLeft,
+            _ => LeaveResult::Unknown(result),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct Leave
+{
+    /* Milliseconds of game time, see `Replay::timeline` */
+    pub time: u32,
+    pub reason: LeaveReason,
+    pub result: LeaveResult,
+    pub session_leave_count: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct PlayerLeave
+{
+    pub player_id: u8,
+    pub player_name: String,
+    /* `None` when the replay ended before the player left */
+    pub leave: Option<Leave>,
+}
+
+impl Replay
+{
+    /// When & how every player in the `GameHeader` left, the replay saver first
+    pub fn leave_report(&self) -> Vec<PlayerLeave>
+    {
+        let leaves = self.timeline().into_iter()
+            .filter_map(|entry| match entry.event
+            {
+                TimelineEvent::Block(&ReplayBlock::LeaveGame { reason, player_id, result, session_leave_count }) =>
+                {
+                    Some((player_id, Leave {
+                        time: entry.time,
+                        reason: LeaveReason::from_u32(reason),
+                        result: LeaveResult::from_u32(result),
+                        session_leave_count,
+                    }))
+                },
+                _ => None,
+            })
+            .collect::<Vec<(u8, Leave)>>();
+
+        Some(&self.game_header.replay_saver).into_iter()
+            .chain(self.game_header.players.iter())
+            .map(|player|
+            {
+                PlayerLeave {
+                    player_id: player.player_id,
+                    player_name: player.player_name.clone(),
+                    leave: leaves.iter()
+                        .find(|&&(player_id, _)| player_id == player.player_id)
+                        .map(|&(_, ref leave)| leave.clone()),
+                }
+            })
+            .collect()
+    }
+}
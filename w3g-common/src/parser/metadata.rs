@@ -0,0 +1,52 @@
+use std::fs::File;
+use std::io::Read;
+
+use super::parser::{ReplayReader, ParseOptions, ParseWarning, ReplayHeader, GameHeader, ReplayBlock};
+
+use ::errors::*;
+
+
+/// Everything about a replay that can be known without decoding its actions
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ReplayMetadata
+{
+    /* Version, build & duration */
+    pub replay_header: ReplayHeader,
+    /* Game name, players & slots */
+    pub game_header: GameHeader,
+    /* Every `ReplayBlock::LeaveGame` in the order they happened */
+    pub leave_games: Vec<ReplayBlock>,
+
+    /* Problems tolerated while parsing leniently, always empty for strict parsing */
+    pub warnings: Vec<ParseWarning>,
+}
+
+/// Cheaper alternative to `parse_replay` for when only the headers and who left are needed, ticks & chat are skipped without being decoded
+pub fn parse_replay_metadata(raw: &mut Read) -> Result<ReplayMetadata>
+{
+    parse_replay_metadata_with_options(raw, ParseOptions::default())
+}
+
+pub fn parse_replay_metadata_with_options(raw: &mut Read, options: ParseOptions) -> Result<ReplayMetadata>
+{
+    let mut reader = ReplayReader::with_options(raw, options)?;
+    let leave_games = reader.scan_leave_games()?;
+    let warnings = reader.warnings().clone();
+
+    Ok(
+        ReplayMetadata
+        {
+            replay_header: reader.replay_header,
+            game_header: reader.game_header,
+            leave_games,
+            warnings,
+        }
+    )
+}
+
+pub fn extract_replay_metadata(path: &str) -> Result<ReplayMetadata>
+{
+    let mut file = File::open(path)?;
+
+    parse_replay_metadata(&mut file)
+}
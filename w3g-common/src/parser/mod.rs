@@ -1,14 +1,42 @@
 pub mod parser;  
+pub mod writer;
+pub mod settings;
+pub mod error;
+pub mod timeline;
+pub mod view;
+pub mod metadata;
+pub mod dictionary;
+pub mod chat;
+pub mod leave;
+pub mod game_cache;
+pub mod w3mmd;
+pub mod slot;
+pub mod encoding;
+mod protobuf;
 
 pub use self::parser::Replay;
+pub use self::parser::ParseMode;
+pub use self::parser::ParseOptions;
+pub use self::parser::ParseWarning;
+pub use self::encoding::StringEncoding;
+pub use self::encoding::Codepage;
+pub use self::error::ParseError;
+pub use self::error::ParseContext;
 pub use self::parser::ReplayHeader;
 pub use self::parser::GameHeader;
+pub use self::parser::ReforgedMetadata;
+pub use self::parser::ReforgedPlayerMetadata;
+pub use self::parser::REFORGED_VERSION_NUMBER;
+pub use self::parser::LEGACY_VERSION_STRING;
 pub use self::parser::PlayerRecord;
 pub use self::parser::GameRecord;
 pub use self::parser::SlotRecord;
 pub use self::parser::ReplayBlock;
 pub use self::parser::Command;
 pub use self::parser::GameSpeed;
+pub use self::settings::GameSettings;
+pub use self::settings::Visibility;
+pub use self::settings::ObserverMode;
 pub use self::parser::OrderType;
 pub use self::parser::SelectionOperation;
 pub use self::parser::AllianceType;
@@ -17,7 +45,58 @@ pub use self::parser::GameObject;
 pub use self::parser::UnitInventory;
 pub use self::parser::UnitAbility;
 pub use self::parser::Action;
+pub use self::dictionary::Dictionary;
+pub use self::chat::ChatMessage;
+pub use self::chat::ChatChannel;
+pub use self::leave::LeaveReason;
+pub use self::leave::LeaveResult;
+pub use self::leave::Leave;
+pub use self::leave::PlayerLeave;
+pub use self::game_cache::GameCacheType;
+pub use self::game_cache::GameCacheValue;
+pub use self::game_cache::GameCacheChange;
+pub use self::game_cache::GameCacheEntry;
+pub use self::game_cache::GameCacheKey;
+pub use self::w3mmd::W3mmd;
+pub use self::w3mmd::W3mmdFlag;
+pub use self::w3mmd::W3mmdType;
+pub use self::w3mmd::W3mmdGoal;
+pub use self::w3mmd::W3mmdSuggestion;
+pub use self::w3mmd::W3mmdValue;
+pub use self::w3mmd::W3mmdVariable;
+pub use self::w3mmd::W3mmdEventDefinition;
+pub use self::w3mmd::W3mmdEvent;
+pub use self::w3mmd::W3mmdPlayer;
+pub use self::w3mmd::W3mmdMessage;
+pub use self::w3mmd::W3mmdWarning;
+pub use self::slot::SlotStatus;
+pub use self::slot::SlotController;
+pub use self::slot::Race;
+pub use self::slot::PlayerColor;
+pub use self::slot::AiStrength;
+pub use self::slot::SlotTeam;
+pub use self::slot::LobbyPlayer;
+pub use self::timeline::TimelineEntry;
+pub use self::timeline::TimelineEvent;
 
 
 pub use self::parser::extract_replay;
-pub use self::parser::parse_replay;
\ No newline at end of file
+pub use self::parser::parse_replay;
+pub use self::parser::parse_replay_with_options;
+pub use self::parser::ReplayReader;
+
+pub use self::metadata::ReplayMetadata;
+pub use self::metadata::extract_replay_metadata;
+pub use self::metadata::parse_replay_metadata;
+pub use self::metadata::parse_replay_metadata_with_options;
+
+pub use self::view::parse_replay_bytes;
+pub use self::view::parse_replay_bytes_with_options;
+pub use self::view::ReplayData;
+pub use self::view::ReplayBlockView;
+pub use self::view::CommandView;
+pub use self::view::ActionView;
+pub use self::view::ReplayBlockViews;
+
+pub use self::writer::write_replay;
+pub use self::writer::save_replay;
\ No newline at end of file
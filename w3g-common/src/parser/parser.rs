@@ -1,63 +1,334 @@
-use byteorder::{ReadBytesExt, LittleEndian};
+use byteorder::{ByteOrder, ReadBytesExt, LittleEndian};
 
 use std::fs::File;
 
+use std::borrow::Cow;
+
 use std::collections::VecDeque;
  
+use std::io;
 use std::io::{Cursor, Read}; 
 
 use libflate::zlib::Decoder;
 
+use rayon::prelude::*;
+
+use crc::crc32;
+
+use super::protobuf;
+use super::protobuf::FieldValue;
+use super::error::{ParseError, ParseContext};
+use super::encoding::{self, StringEncoding};
+use super::view::{ReplayBlockView, CommandView, ActionView};
+
 use ::errors::*;
 
 
-/// Size of '\0' that commonly occurs at the end of a String
-const NULL_BYTE_LENGTH: usize = 1;
+/// Size of the part of the header that is shared by every `header_version`
+const BASE_HEADER_SIZE: usize = 0x30;
+
+/// Size of the whole header with `header_version` 1, the largest there is (`header_version` 0 is 0x40)
+const MAX_HEADER_SIZE: usize = 0x44;
+
+/// `ReplayHeader.version_number` of the first Reforged patch (1.32), older patches use their minor version (e.g. 30 for 1.30)
+pub const REFORGED_VERSION_NUMBER: u32 = 10032;
+
+/// Every replay with `header_version` 0 is Reign of Chaos ("WAR3" stored backwards)
+pub const LEGACY_VERSION_STRING: &'static str = "3RAW";
+
+/// Orders started carrying 2 unknown object ids in 1.07
+pub(crate) const ORDER_OBJECT_VERSION_NUMBER: u32 = 7;
+
+/// Order flags were widened from a byte to a word in 1.13
+pub(crate) const ORDER_FLAGS_WORD_VERSION_NUMBER: u32 = 13;
+
+/// `SlotRecord.ai_strength` was added in 1.03, slot records are 7 bytes before that
+pub(crate) const SLOT_AI_STRENGTH_VERSION_NUMBER: u32 = 3;
+
+/// `SlotRecord.handicap` was added in 1.07, slot records are 8 bytes before that
+pub(crate) const SLOT_HANDICAP_VERSION_NUMBER: u32 = 7;
+
+/// What slots from before `ai_strength` & `handicap` existed are given, i.e. normal & no handicap
+const DEFAULT_AI_STRENGTH: u8 = 1;
+const DEFAULT_HANDICAP: u8 = 100;
+
+/// `ReplayBlock::PlayerChat.flags` of messages sent before the game started, they don't store a `chat_mode`
+pub(crate) const LOBBY_CHAT_FLAGS: u8 = 0x10;
+
+/// 0x19 switched from a subgroup index to an item & object id in 1.14b (1.14 itself isn't distinguished)
+const SELECT_SUBGROUP_ITEM_VERSION_NUMBER: u32 = 14;
+
+/// How the parser should react to data that is damaged but still readable
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum ParseMode
+{
+    /* Fail the whole replay */
+    Strict,
+    /* Keep going and record a `ParseWarning` on the `Replay` */
+    Lenient,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone, new)]
+pub struct ParseOptions
+{
+    pub mode: ParseMode,
+    /* Read every block up front and inflate them across all cores instead of one at a time as they're needed */
+    #[new(default)]
+    #[serde(default)]
+    pub parallel_decompression: bool,
+    /* Names, game names & chat that aren't valid UTF-8 */
+    #[new(default)]
+    #[serde(default)]
+    pub string_encoding: StringEncoding,
+}
+
+impl Default for ParseOptions
+{
+    fn default() -> ParseOptions
+    {
+        ParseOptions::new(ParseMode::Strict)
+    }
+}
+
+/// Problems that were tolerated because the replay was parsed with `ParseMode::Lenient`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub enum ParseWarning
+{
+    HeaderChecksumMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    BlockChecksumMismatch {
+        /* 0-indexed position of the compressed block within the file */
+        block_index: u32,
+        expected: u32,
+        actual: u32,
+    },
+    /* The rest of the command was kept as `Action::Unknown` */
+    UnknownAction {
+        player_id: u8,
+        action_id: u8,
+        /* Including the action id */
+        skipped_bytes: usize,
+    },
+}
 
 pub fn parse_replay(raw: &mut Read) -> Result<Replay>
 {
-    let magic_string = extract_fixed_length_string(raw, 28)?;
-    let file_offset = extract_unsigned_dword(raw)?;
-    let compressed_size = extract_unsigned_dword(raw)?;
-    let header_version = extract_unsigned_dword(raw)?;
-    let decompressed_size = extract_unsigned_dword(raw)?;
-    let number_of_compressed_blocks = extract_unsigned_dword(raw)?;
-
-    let replay_header = extract_replay_header(raw)?;
-    let mut stream = ReplayStream::from_file(raw);
-
-    let game_header = stream.extract_game_header()?;
-    let replay_blocks = stream.extract_blocks()?;
+    parse_replay_with_options(raw, ParseOptions::default())
+}
+
+pub fn parse_replay_with_options(raw: &mut Read, options: ParseOptions) -> Result<Replay>
+{
+    let mut reader = ReplayReader::with_options(raw, options)?;
+    let replay_blocks = reader.by_ref().collect::<Result<Vec<ReplayBlock>>>()?;
+
+    Ok(reader.into_replay(replay_blocks))
+}
+
+pub fn extract_replay(path: &str) -> Result<Replay>
+{
+    let mut file = File::open(path)?;
     
-    Ok(
+    parse_replay(&mut file)
+}
+
+/// Pull based alternative to `parse_replay`.
+/// 
+/// The headers are parsed up front and exposed as fields, then each `ReplayBlock` is decoded only when `next()` is called. 
+/// Consumers that only need part of a replay can stop iterating early and nothing after that point is decompressed.
+pub struct ReplayReader<R: Read>
+{
+    pub magic_string: String,
+    pub file_offset: u32,
+    pub compressed_size: u32,
+    pub header_version: u32,
+    pub decompressed_size: u32,
+    pub number_of_compressed_blocks: u32,
+
+    pub replay_header: ReplayHeader,
+    pub game_header: GameHeader,
+
+    stream: ReplayStream<R>,
+    /* Set once the terminating block (or an error) has been hit so the iterator stays fused */
+    finished: bool,
+}
+
+impl<R: Read> ReplayReader<R>
+{
+    /// Parses everything up to and including the `GameHeader`, leaving the stream positioned at the first `ReplayBlock`
+    pub fn new(raw: R) -> Result<ReplayReader<R>>
+    {
+        ReplayReader::with_options(raw, ParseOptions::default())
+    }
+
+    pub fn with_options(mut raw: R, options: ParseOptions) -> Result<ReplayReader<R>>
+    {
+        let mut header = vec![0u8; BASE_HEADER_SIZE];
+        raw.read_exact(&mut header)?;
+
+        let (magic_string, file_offset, compressed_size, header_version, decompressed_size, number_of_compressed_blocks) = 
+        {
+            let mut cursor = Cursor::new(&header[..]);
+            (
+                extract_fixed_length_string(&mut cursor, 28, options.string_encoding)?,
+                extract_unsigned_dword(&mut cursor)?,
+                extract_unsigned_dword(&mut cursor)?,
+                extract_unsigned_dword(&mut cursor)?,
+                extract_unsigned_dword(&mut cursor)?,
+                extract_unsigned_dword(&mut cursor)?,
+            )
+        };
+
+        /* file_offset is the size of the header, anything bigger than the known headers isn't worth allocating */
+        if (file_offset as usize) < BASE_HEADER_SIZE || (file_offset as usize) > MAX_HEADER_SIZE
+        {
+            bail!(ErrorKind::Parse(ParseError::InvalidValue { context: ParseContext::default(), type_name: String::from("file_offset"), value: file_offset as u64 }));
+        }
+        header.resize(file_offset as usize, 0);
+        raw.read_exact(&mut header[BASE_HEADER_SIZE..])?;
+
+        let replay_header = match header_version
+        {
+            0 => extract_legacy_replay_header(&mut Cursor::new(&header[BASE_HEADER_SIZE..]))?,
+            1 => extract_replay_header(&mut Cursor::new(&header[BASE_HEADER_SIZE..]), options.string_encoding)?,
+            _ => bail!(format!("{} is not a supported header_version", header_version)),
+        };
+        let mut stream = ReplayStream::from_file(raw, options, replay_header.version_number);
+
+        /* The crc is computed over the header with the crc itself zeroed */
+        let crc_offset = header.len() - 4;
+        for byte in header[crc_offset..].iter_mut()
+        {
+            *byte = 0;
+        }
+        let actual = crc32::checksum_ieee(&header);
+        if actual != replay_header.crc32
+        {
+            stream.report(ParseWarning::HeaderChecksumMismatch { expected: replay_header.crc32, actual })?;
+        }
+
+        if options.parallel_decompression
+        {
+            let decompressed = stream.decompress_in_parallel(number_of_compressed_blocks);
+            stream.add_context(decompressed)?;
+        }
+
+        let game_header = stream.extract_game_header();
+        let game_header = stream.add_context(game_header)?;
+
+        Ok(
+            ReplayReader
+            {
+                magic_string,
+                file_offset,
+                compressed_size,
+                header_version,
+                decompressed_size,
+                number_of_compressed_blocks,
+
+                replay_header,
+                game_header,
+
+                stream,
+                finished: false,
+            }
+        )
+    }
+
+    /// Combines the already parsed headers with `replay_blocks` (typically whatever was collected from this reader)
+    pub fn into_replay(self, replay_blocks: Vec<ReplayBlock>) -> Replay
+    {
         Replay
         { 
-            magic_string,
-            file_offset,
-            compressed_size, 
-            header_version,
-            decompressed_size, 
-            number_of_compressed_blocks,
-
-            replay_header,
-            game_header,
+            magic_string: self.magic_string,
+            file_offset: self.file_offset,
+            compressed_size: self.compressed_size, 
+            header_version: self.header_version,
+            decompressed_size: self.decompressed_size, 
+            number_of_compressed_blocks: self.number_of_compressed_blocks,
+
+            replay_header: self.replay_header,
+            game_header: self.game_header,
             replay_blocks,
+
+            warnings: self.stream.warnings,
         }
-    )
+    }
+
+    /// Problems tolerated so far, only ever populated when parsing with `ParseMode::Lenient`
+    pub fn warnings(&self) -> &Vec<ParseWarning>
+    {
+        &self.stream.warnings
+    }
+
+    /// Decompresses every block that hasn't been read yet into one buffer instead of parsing them
+    pub(crate) fn decompress_remaining(&mut self) -> Result<Vec<u8>>
+    {
+        self.finished = true;
+
+        let data = self.stream.decompress_remaining(self.number_of_compressed_blocks);
+        self.stream.add_context(data)
+    }
+
+    /// Skims the remaining blocks for `ReplayBlock::LeaveGame` without decoding anything else
+    pub(crate) fn scan_leave_games(&mut self) -> Result<Vec<ReplayBlock>>
+    {
+        self.finished = true;
+
+        let mut leave_games = Vec::new();
+        loop
+        {
+            let block = self.stream.next_leave_game();
+            match self.stream.add_context(block)?
+            {
+                Some(block) => leave_games.push(block),
+                None => return Ok(leave_games),
+            }
+        }
+    }
+
+    /// How many bytes of decompressed data have been consumed, i.e. the size of the `GameHeader` right after construction
+    pub(crate) fn offset(&self) -> u64
+    {
+        self.stream.context.offset
+    }
 }
 
-pub fn extract_replay(path: &str) -> Result<Replay>
+impl<R: Read> Iterator for ReplayReader<R>
 {
-    let mut file = File::open(path)?;
-    
-    parse_replay(&mut file)
+    type Item = Result<ReplayBlock>;
+
+    fn next(&mut self) -> Option<Result<ReplayBlock>>
+    {
+        if self.finished
+        {
+            return None;
+        }
+
+        let block = self.stream.extract_block();
+        match self.stream.add_context(block)
+        {
+            Ok(Some(block)) => Some(Ok(block)),
+            Ok(None) =>
+            {
+                self.finished = true;
+                None
+            },
+            Err(error) =>
+            {
+                self.finished = true;
+                Some(Err(error))
+            },
+        }
+    }
 }
 
-fn extract_replay_header(file: &mut Read) -> Result<ReplayHeader>
+fn extract_replay_header(file: &mut Read, string_encoding: StringEncoding) -> Result<ReplayHeader>
 {
     Ok(
         ReplayHeader {
-            version_string: extract_fixed_length_string(file, 4)?,
+            version_string: extract_fixed_length_string(file, 4, string_encoding)?,
             version_number: extract_unsigned_dword(file)?,
             build_number: extract_unsigned_word(file)?,
             flags: extract_unsigned_word(file)?,
@@ -67,6 +338,23 @@ fn extract_replay_header(file: &mut Read) -> Result<ReplayHeader>
     )
 }
 
+/// Reign of Chaos before 1.07 (`header_version` 0) only stored a word for the version and didn't have a version string
+fn extract_legacy_replay_header(file: &mut Read) -> Result<ReplayHeader>
+{
+    let _unknown = extract_unsigned_word(file)?;
+
+    Ok(
+        ReplayHeader {
+            version_string: String::from(LEGACY_VERSION_STRING),
+            version_number: extract_unsigned_word(file)? as u32,
+            build_number: extract_unsigned_word(file)?,
+            flags: extract_unsigned_word(file)?,
+            duration: extract_unsigned_dword(file)?,
+            crc32: extract_unsigned_dword(file)?,
+        }
+    )
+}
+
 fn extract_unsigned_word(file: &mut Read) -> Result<u16>
 {
     let mut buffer = vec![0u8; 2];
@@ -83,48 +371,301 @@ fn extract_unsigned_dword(file: &mut Read) -> Result<u32>
     Ok(Cursor::new(buffer).read_u32::<LittleEndian>()?)
 }
 
-fn extract_fixed_length_string(file: &mut Read, length: usize) -> Result<String>
+fn inflate(compressed_data: Vec<u8>, decompressed_size: usize) -> Result<Vec<u8>>
+{
+    let mut decompressed_data = Vec::with_capacity(decompressed_size);
+
+    let mut decoder = Decoder::new(Cursor::new(compressed_data))?;
+    decoder.read_to_end(&mut decompressed_data)?;
+
+    Ok(decompressed_data)
+}
+
+/// `num_bytes` of a block less the fields that come before its data, failing when `num_bytes` can't even hold those
+fn data_size(num_bytes: u16, header_size: usize, context: &ParseContext) -> Result<usize>
+{
+    match (num_bytes as usize).checked_sub(header_size)
+    {
+        Some(size) => Ok(size),
+        None => bail!(ErrorKind::Parse(ParseError::InvalidValue { context: context.clone(), type_name: String::from("block size"), value: num_bytes as u64 })),
+    }
+}
+
+/// The language isn't known yet so `StringEncoding::Guess` is lossy here
+fn extract_fixed_length_string(file: &mut Read, length: usize, string_encoding: StringEncoding) -> Result<String>
 {
     let mut buffer = vec![0u8; length];
     file.read_exact(&mut buffer)?;
-        
-    Ok(String::from_utf8(buffer)?)
+
+    match encoding::decode_string(buffer, string_encoding, None)
+    {
+        Some((string, _)) => Ok(string),
+        None => bail!(ErrorKind::Parse(ParseError::InvalidUtf8 { context: ParseContext::default() })),
+    }
+}
+
+/// Everything in a 0x6E after its file, group & key, shared by `Action::SyncStoredUnit` & `ActionView::SyncStoredUnit`
+struct StoredUnit
+{
+    unit_type: u32,
+    inventory: Vec<UnitInventory>,
+    experience: u32,
+    level_ups: u32,
+    skill_points: u32,
+    proper_name_index: u16,
+    unknown1: u16,
+    base_strength: u32,
+    bonus_strength_per_level: f32,
+    base_agility: u32,
+    bonus_move_speed: f32,
+    bonus_attack_speed: f32,
+    bonus_agility_per_level: f32,
+    base_intelligence: u32,
+    bonus_intelligence_per_level: f32,
+    abilities: Vec<UnitAbility>,
+    bonus_health: f32,
+    bonus_mana: f32,
+    sight_radius_day: f32,
+    unknown2: u32,
+    unknown3: u32,
+    unknown4: u32,
+    unknown5: u32,
+    hotkey_flags: u16,
+}
+
+/// A block read up to its variable length data, see `ReplayStream::extract_block_header`
+pub(crate) enum BlockHeader
+{
+    /* 0x0, nothing comes after it */
+    End,
+    /* Blocks that are nothing but fixed size fields */
+    Fixed(ReplayBlock),
+    /* 0x1E & 0x1F, followed by `commands_size` bytes of commands */
+    Tick {
+        pre_overflow: bool,
+        num_bytes: u16,
+        time_increment: u16,
+        commands_size: usize,
+    },
+    /* 0x20, followed by a `message_size` byte message and its '\0' */
+    PlayerChat {
+        player_id: u8,
+        num_bytes: u16,
+        flags: u8,
+        chat_mode: u32,
+        message_size: usize,
+    },
 }
 
-struct ReplayStream<R: Read>
+pub(crate) struct ReplayStream<R: Read>
 {
     raw_file: R,
     decompressed_bytes: VecDeque<u8>,
+    /* `raw_file` is already decompressed and is read directly, see `from_slice` */
+    decompressed: bool,
+
+    options: ParseOptions,
+    warnings: Vec<ParseWarning>,
+    blocks_read: u32,
+
+    /* Layouts change between patches, see `ReplayHeader.version_number` */
+    version_number: u32,
+    /* `GameHeader.language_id` once it's been read, for `StringEncoding::Guess` */
+    language_id: Option<u32>,
+
+    /* Where parsing is up to, attached to any error that escapes */
+    context: ParseContext,
 }
 
 impl<R: Read> ReplayStream<R>
 {
-    fn from_file(file: R) -> ReplayStream<R>
+    fn from_file(file: R, options: ParseOptions, version_number: u32) -> ReplayStream<R>
     {
         ReplayStream
         {
             raw_file: file,
             decompressed_bytes: VecDeque::new(),
+            decompressed: false,
+
+            options,
+            warnings: Vec::new(),
+            blocks_read: 0,
+
+            version_number,
+            language_id: None,
+
+            context: ParseContext::default(),
+        }
+    }
+
+    /// Everything that's left: what was already decompressed followed by the rest of the `number_of_blocks` blocks
+    fn decompress_remaining(&mut self, number_of_blocks: u32) -> Result<Vec<u8>>
+    {
+        while self.blocks_read < number_of_blocks
+        {
+            self.decompress_data()?;
+        }
+
+        Ok(self.decompressed_bytes.drain(..).collect())
+    }
+
+    pub(crate) fn into_warnings(self) -> Vec<ParseWarning>
+    {
+        self.warnings
+    }
+
+    /// Reforged (>= 1.32) changed the block header and added new records
+    fn is_reforged(&self) -> bool
+    {
+        self.version_number >= REFORGED_VERSION_NUMBER
+    }
+
+    /// Fails when parsing strictly, otherwise remembers `warning` and carries on
+    fn report(&mut self, warning: ParseWarning) -> Result<()>
+    {
+        match self.options.mode
+        {
+            ParseMode::Strict => bail!(ErrorKind::Parse(ParseError::Rejected { context: self.context.clone(), warning })),
+            ParseMode::Lenient =>
+            {
+                warn!("Ignoring {:?}", warning);
+                self.warnings.push(warning);
+                Ok(())
+            },
         }
     }
 
+    /// Turns any error from parsing the decompressed data into an `ErrorKind::Parse` saying where it happened, keeping the original as its cause
+    pub(crate) fn add_context<T>(&self, result: Result<T>) -> Result<T>
+    {
+        result.map_err(|error|
+        {
+            let context = self.context.clone();
+            let parse_error = match *error.kind()
+            {
+                ErrorKind::Parse(_) => return error,
+                ErrorKind::InvalidValue(ref type_name, value) => ParseError::InvalidValue { context, type_name: type_name.clone(), value },
+                ErrorKind::Io(ref io_error) if io_error.kind() == io::ErrorKind::UnexpectedEof => ParseError::UnexpectedEnd { context },
+                ErrorKind::Utf8(_) => ParseError::InvalidUtf8 { context },
+                _ => ParseError::Other { context, message: error.to_string() },
+            };
+
+            Error::with_chain(error, ErrorKind::Parse(parse_error))
+        })
+    }
+
     fn decompress_data(&mut self) -> Result<()>
     {
-        let compressed_size = extract_unsigned_word(&mut self.raw_file)? as usize;
-        let decompressed_size = extract_unsigned_word(&mut self.raw_file)? as usize;
-        let _crc32 = extract_unsigned_dword(&mut self.raw_file)?;
+        let (compressed_data, decompressed_size) = self.read_block_frame()?;
+        let decompressed_data = inflate(compressed_data, decompressed_size)?;
+        self.decompressed_bytes.extend(decompressed_data);
+
+        Ok(())
+    }
+
+    /// Reads the frames of every block that hasn't been read yet and inflates them across all cores
+    fn decompress_in_parallel(&mut self, number_of_blocks: u32) -> Result<()>
+    {
+        let mut frames = Vec::with_capacity(number_of_blocks.saturating_sub(self.blocks_read) as usize);
+        while self.blocks_read < number_of_blocks
+        {
+            frames.push(self.read_block_frame()?);
+        }
+
+        let blocks = frames.into_par_iter()
+            .map(|(compressed_data, decompressed_size)| inflate(compressed_data, decompressed_size))
+            .collect::<Result<Vec<Vec<u8>>>>()?;
+        for block in blocks
+        {
+            self.decompressed_bytes.extend(block);
+        }
+
+        Ok(())
+    }
+
+    /// Reads the next block's header & compressed data, verifying its checksum
+    /// 
+    /// # Return
+    /// * The compressed data and how big it'll be once inflated
+    fn read_block_frame(&mut self) -> Result<(Vec<u8>, usize)>
+    {
+        /* Reforged widened the sizes to dwords, the crc is computed the same way over the longer header */
+        let reforged = self.is_reforged();
+        let mut block_header = vec![0u8; if reforged { 12 } else { 8 }];
+        self.raw_file.read_exact(&mut block_header)?;
+
+        let (compressed_size, decompressed_size, crc32) = 
+        {
+            let mut cursor = Cursor::new(&block_header[..]);
+            if reforged
+            {
+                (
+                    extract_unsigned_dword(&mut cursor)? as usize,
+                    extract_unsigned_dword(&mut cursor)? as usize,
+                    extract_unsigned_dword(&mut cursor)?,
+                )
+            } else
+            {
+                (
+                    extract_unsigned_word(&mut cursor)? as usize,
+                    extract_unsigned_word(&mut cursor)? as usize,
+                    extract_unsigned_dword(&mut cursor)?,
+                )
+            }
+        };
 
         let mut compressed_data = vec![0u8; compressed_size];
         self.raw_file.read_exact(&mut compressed_data)?;
+
+        /*
+            Low word is the crc of the block header (with the crc zeroed) and the high word is the crc of the compressed data.
+            Each crc32 is folded in half by xor'ing its words together.
+        */
+        let crc_offset = block_header.len() - 4;
+        for byte in block_header[crc_offset..].iter_mut()
+        {
+            *byte = 0;
+        }
+        let header_crc = crc32::checksum_ieee(&block_header);
+        let data_crc = crc32::checksum_ieee(&compressed_data);
+        let actual = ((header_crc ^ (header_crc >> 16)) & 0xFFFF) | (((data_crc ^ (data_crc >> 16)) & 0xFFFF) << 16);
+
+        let block_index = self.blocks_read;
+        self.blocks_read = self.blocks_read + 1;
+        if actual != crc32
+        {
+            self.report(ParseWarning::BlockChecksumMismatch { block_index, expected: crc32, actual })?;
+        }
         
-        let mut decompressed_data = Vec::with_capacity(decompressed_size);
+        Ok((compressed_data, decompressed_size))
+    }
+
+    /// Fills `buffer` with the next bytes, decompressing more blocks as needed
+    fn fill_bytes(&mut self, buffer: &mut [u8]) -> Result<()>
+    {
+        if self.decompressed
+        {
+            self.raw_file.read_exact(buffer)?;
+            self.context.offset = self.context.offset + buffer.len() as u64;
 
-        let mut decoder = Decoder::new(Cursor::new(compressed_data))?;
-        decoder.read_to_end(&mut decompressed_data)?;
+            return Ok(());
+        }
 
-        for x in decompressed_data
+        let mut filled = 0;
+        while filled < buffer.len()
         {
-            self.decompressed_bytes.push_back(x);
+            if self.decompressed_bytes.len() < 1
+            {
+                self.decompress_data()?;
+            }
+
+            let count = (buffer.len() - filled).min(self.decompressed_bytes.len());
+            for (target, byte) in buffer[filled..filled + count].iter_mut().zip(self.decompressed_bytes.drain(..count))
+            {
+                *target = byte;
+            }
+            filled = filled + count;
+            self.context.offset = self.context.offset + count as u64;
         }
 
         Ok(())
@@ -132,53 +673,92 @@ impl<R: Read> ReplayStream<R>
 
     fn read_bytes(&mut self, length: usize) -> Result<Vec<u8>>
     {
-        let mut buffer = Vec::with_capacity(length);
+        let mut buffer = vec![0u8; length];
+        self.fill_bytes(&mut buffer)?;
+
+        Ok(buffer)
+    }
+
+    fn skip_bytes(&mut self, length: usize) -> Result<()>
+    {
+        if self.decompressed
+        {
+            let skipped = io::copy(&mut self.raw_file.by_ref().take(length as u64), &mut io::sink())?;
+            self.context.offset = self.context.offset + skipped;
+            if skipped < length as u64
+            {
+                bail!(ErrorKind::Parse(ParseError::UnexpectedEnd { context: self.context.clone() }));
+            }
 
-        for _ in 0..length
+            return Ok(());
+        }
+
+        let mut remaining = length;
+        while remaining > 0
         {
             if self.decompressed_bytes.len() < 1
             {
                 self.decompress_data()?;
             }
 
-            buffer.push(self.decompressed_bytes.pop_front()
-                .ok_or("no data left in decompressed bytes")?);
+            let count = remaining.min(self.decompressed_bytes.len());
+            self.decompressed_bytes.drain(..count);
+            remaining = remaining - count;
+            self.context.offset = self.context.offset + count as u64;
         }
 
-        Ok(buffer)
+        Ok(())
     }
 
     fn read_float32(&mut self) -> Result<f32>
     {
-        Ok(Cursor::new(self.read_bytes(4)?).read_f32::<LittleEndian>()?)
+        let mut buffer = [0u8; 4];
+        self.fill_bytes(&mut buffer)?;
+
+        Ok(LittleEndian::read_f32(&buffer))
     }
 
     fn read_signed_dword(&mut self) -> Result<i32>
     {
-        Ok(Cursor::new(self.read_bytes(4)?).read_i32::<LittleEndian>()?)
+        let mut buffer = [0u8; 4];
+        self.fill_bytes(&mut buffer)?;
+
+        Ok(LittleEndian::read_i32(&buffer))
     }
 
     fn read_unsigned_dword(&mut self) -> Result<u32>
     {
-        Ok(Cursor::new(self.read_bytes(4)?).read_u32::<LittleEndian>()?)
+        let mut buffer = [0u8; 4];
+        self.fill_bytes(&mut buffer)?;
+
+        Ok(LittleEndian::read_u32(&buffer))
     }
 
     fn read_unsigned_qword(&mut self) -> Result<u64>
     {
-        Ok(Cursor::new(self.read_bytes(8)?).read_u64::<LittleEndian>()?)
+        let mut buffer = [0u8; 8];
+        self.fill_bytes(&mut buffer)?;
+
+        Ok(LittleEndian::read_u64(&buffer))
     }
 
     fn read_unsigned_word(&mut self) -> Result<u16>
     {
-        Ok(Cursor::new(self.read_bytes(2)?).read_u16::<LittleEndian>()?)
+        let mut buffer = [0u8; 2];
+        self.fill_bytes(&mut buffer)?;
+
+        Ok(LittleEndian::read_u16(&buffer))
     }
 
     fn read_unsigned_byte(&mut self) -> Result<u8>
     {
-        Ok(Cursor::new(self.read_bytes(1)?).read_u8()?)
+        let mut buffer = [0u8; 1];
+        self.fill_bytes(&mut buffer)?;
+
+        Ok(buffer[0])
     }
 
-    fn read_null_terminated_string(&mut self) -> Result<String>
+    fn read_null_terminated_bytes(&mut self) -> Result<Vec<u8>>
     {
         let mut buffer = vec!();
         
@@ -189,7 +769,33 @@ impl<R: Read> ReplayStream<R>
             last_byte = self.read_unsigned_byte()?;
         }
         
-        Ok(String::from_utf8(buffer)?)
+        Ok(buffer)
+    }
+
+    /// The original bytes aren't kept, use `read_raw_string` where they should be
+    fn read_null_terminated_string(&mut self) -> Result<String>
+    {
+        let buffer = self.read_null_terminated_bytes()?;
+
+        Ok(self.decode_string(buffer)?.0)
+    }
+
+    /// A string along with its original bytes when they weren't valid UTF-8
+    fn read_raw_string(&mut self) -> Result<(String, Option<Vec<u8>>)>
+    {
+        let buffer = self.read_null_terminated_bytes()?;
+
+        self.decode_string(buffer)
+    }
+
+    /// See `ParseOptions.string_encoding`
+    fn decode_string(&self, bytes: Vec<u8>) -> Result<(String, Option<Vec<u8>>)>
+    {
+        match encoding::decode_string(bytes, self.options.string_encoding, self.language_id)
+        {
+            Some(decoded) => Ok(decoded),
+            None => bail!(ErrorKind::Parse(ParseError::InvalidUtf8 { context: self.context.clone() })),
+        }
     }
 
     fn read_encoded_string(&mut self) -> Result<Vec<u8>>
@@ -210,20 +816,44 @@ impl<R: Read> ReplayStream<R>
     fn extract_game_header(&mut self) -> Result<GameHeader>
     {
         let unknown = self.read_unsigned_dword()?;
-        let replay_saver = self.extract_player_record(None)?;
-        let game_name = self.read_null_terminated_string()?;
+        let mut replay_saver = self.extract_player_record(None)?;
+        let (mut game_name, mut raw_game_name) = self.read_raw_string()?;
         let _null_byte = self.read_bytes(1);
         let encoded_string = self.read_encoded_string()?;
         let number_of_players = self.read_unsigned_dword()?;
         let game_type = self.read_unsigned_dword()?;
         let language_id = self.read_unsigned_dword()?;
+        self.language_id = Some(language_id);
+
+        /* Both came before the language so they're decoded again now that the codepage can be guessed */
+        if let Some(raw_player_name) = replay_saver.raw_player_name.take()
+        {
+            let (player_name, raw_player_name) = self.decode_string(raw_player_name)?;
+            replay_saver.player_name = player_name;
+            replay_saver.raw_player_name = raw_player_name;
+        }
+        if let Some(raw) = raw_game_name.take()
+        {
+            let (name, raw) = self.decode_string(raw)?;
+            game_name = name;
+            raw_game_name = raw;
+        }
         
         let mut players = Vec::with_capacity(number_of_players as usize);
         let mut record_id = self.read_unsigned_byte()?;
         while record_id == 0x16
         {
-            players.push(self.extract_player_record(Some(record_id))?);
-            let _ = self.read_unsigned_dword()?;
+            let mut player = self.extract_player_record(Some(record_id))?;
+            player.unknown = Some(self.read_unsigned_dword()?);
+            players.push(player);
+
+            record_id = self.read_unsigned_byte()?;
+        }
+
+        let mut reforged_metadata = Vec::new();
+        while self.is_reforged() && record_id == 0x39
+        {
+            reforged_metadata.push(self.extract_reforged_metadata()?);
 
             record_id = self.read_unsigned_byte()?;
         }
@@ -235,16 +865,32 @@ impl<R: Read> ReplayStream<R>
                 unknown,
                 replay_saver,
                 game_name,
+                raw_game_name,
                 encoded_string,
                 number_of_players,
                 game_type,
                 language_id,
                 players,
+                reforged_metadata,
                 game_record,
             }
         )
     }
 
+    /// Reads a 0x39 record, the record id must already have been read
+    fn extract_reforged_metadata(&mut self) -> Result<ReforgedMetadata>
+    {
+        let subtype = self.read_unsigned_byte()?;
+        let num_bytes = self.read_unsigned_dword()?;
+        let data = self.read_bytes(num_bytes as usize)?;
+
+        match subtype
+        {
+            0x03 => Ok(ReforgedMetadata::Player(ReforgedPlayerMetadata::from_protobuf(&data)?)),
+            _ => Ok(ReforgedMetadata::Unknown { subtype, data }),
+        }
+    }
+
     fn extract_player_record(&mut self, record_id: Option<u8>) -> Result<PlayerRecord>
     {
         let record_id = match record_id {
@@ -252,7 +898,7 @@ impl<R: Read> ReplayStream<R>
             None => self.read_unsigned_byte()?,
         };
         let player_id = self.read_unsigned_byte()?;
-        let player_name = self.read_null_terminated_string()?;
+        let (player_name, raw_player_name) = self.read_raw_string()?;
         let additional_data_size = self.read_unsigned_byte()?;
         let additional_data = self.read_bytes(additional_data_size as usize)?;
         
@@ -261,8 +907,10 @@ impl<R: Read> ReplayStream<R>
                 record_id,
                 player_id,
                 player_name,
+                raw_player_name,
                 additional_data_size,
                 additional_data,
+                unknown: None,
             }
         )
     }
@@ -332,148 +980,201 @@ impl<R: Read> ReplayStream<R>
                 team_number: self.read_unsigned_byte()?,
                 color: self.read_unsigned_byte()?,
                 race: self.read_unsigned_byte()?,
-                ai_strength: self.read_unsigned_byte()?,
-                handicap: self.read_unsigned_byte()?,
+                ai_strength: if self.version_number >= SLOT_AI_STRENGTH_VERSION_NUMBER { self.read_unsigned_byte()? } else { DEFAULT_AI_STRENGTH },
+                handicap: if self.version_number >= SLOT_HANDICAP_VERSION_NUMBER { self.read_unsigned_byte()? } else { DEFAULT_HANDICAP },
             }
         )
     }
 
-    fn extract_blocks(&mut self) -> Result<Vec<ReplayBlock>>
+    /// Reads the next block out of the stream, skipping over block ids that aren't understood.
+    /// 
+    /// # Return
+    /// * `None` once the terminating `0x0` block id has been read
+    /// Reads a block up to its variable length data, skipping block ids that aren't known. Every way of reading the blocks goes through here.
+    pub(crate) fn extract_block_header(&mut self) -> Result<BlockHeader>
     {
-        let mut blocks = Vec::new();
-
-        let mut block_id = self.read_unsigned_byte()?;
-        while block_id != 0x0
+        loop
         {
+            self.context.block_id = None;
+            self.context.player_id = None;
+            self.context.action_id = None;
 
-            match block_id 
+            let block_id = self.read_unsigned_byte()?;
+            self.context.block_id = Some(block_id);
+
+            let block = match block_id 
             {
+                0x0 => return Ok(BlockHeader::End),
                 0x17 => 
                 {
-                    blocks.push(
-                        ReplayBlock::LeaveGame { 
-                            reason: self.read_unsigned_dword()?, 
-                            player_id: self.read_unsigned_byte()?, 
-                            result: self.read_unsigned_dword()?, 
-                            session_leave_count: self.read_unsigned_dword()?,
-                        }
-                    );
+                    ReplayBlock::LeaveGame { 
+                        reason: self.read_unsigned_dword()?, 
+                        player_id: self.read_unsigned_byte()?, 
+                        result: self.read_unsigned_dword()?, 
+                        session_leave_count: self.read_unsigned_dword()?,
+                    }
                 },
                 0x1A =>
                 {
-                    blocks.push(
-                        ReplayBlock::LoadStarted1 { 
-                            unknown: self.read_unsigned_dword()?, 
-                        }
-                    );
+                    ReplayBlock::LoadStarted1 { 
+                        unknown: self.read_unsigned_dword()?, 
+                    }
                 },
                 0x1B =>
                 {
-                    blocks.push(
-                        ReplayBlock::LoadStarted2 { 
-                            unknown: self.read_unsigned_dword()?, 
-                        }
-                    );
+                    ReplayBlock::LoadStarted2 { 
+                        unknown: self.read_unsigned_dword()?, 
+                    }
                 },
                 0x1C =>
                 {
-                    blocks.push(
-                        ReplayBlock::GameStarted { 
-                            unknown: self.read_unsigned_dword()?, 
-                        }
-                    );
+                    ReplayBlock::GameStarted { 
+                        unknown: self.read_unsigned_dword()?, 
+                    }
                 },
-                0x1E =>
+                /* Reforged kept the layout of 0x1E & 0x1F, what it added is new action ids inside the commands, see `extract_action` */
+                0x1E | 0x1F =>
                 {
                     let num_bytes = self.read_unsigned_word()?;
                     let time_increment = self.read_unsigned_word()?;
-                    // minus 2 because 2 for time_increment
-                    let commands = self.extract_commands((num_bytes - 2) as usize)?;
 
-                    blocks.push(
-                        ReplayBlock::TickPreOverflow { 
+                    return Ok(
+                        BlockHeader::Tick {
+                            pre_overflow: block_id == 0x1E,
                             num_bytes,
                             time_increment,
-                            commands,
+                            // minus 2 because 2 for time_increment
+                            commands_size: data_size(num_bytes, 2, &self.context)?,
                         }
                     );
-                }
-                0x1F =>
-                {
-                    let num_bytes = self.read_unsigned_word()?;
-                    let time_increment = self.read_unsigned_word()?;
-                    // minus 2 because 2 for time_increment
-                    let commands = self.extract_commands((num_bytes - 2) as usize)?;
-
-                    blocks.push(
-                        ReplayBlock::Tick { 
-                            num_bytes,
-                            time_increment,
-                            commands,
-                        }
-                    );
-                }
+                },
                 0x20 =>
                 {
                     let player_id = self.read_unsigned_byte()?;
+                    self.context.player_id = Some(player_id);
                     let num_bytes = self.read_unsigned_word()?;
                     let flags = self.read_unsigned_byte()?;
-                    let chat_mode = self.read_unsigned_dword()?;
-                    // minus 6 because 1 for flags, 4 for chat_mode, 1 for '\0'
-                    let message = String::from_utf8(self.read_bytes((num_bytes as usize) - 6)?)?;
-
-                    let ending_byte = self.read_unsigned_byte()?;
-                    if ending_byte != 0x0
+                    let (chat_mode, message_size) = if flags == LOBBY_CHAT_FLAGS
                     {
-                        bail!(String::from("String did not end in \0"));
-                    }
+                        // minus 2 because 1 for flags, 1 for '\0'
+                        (0, data_size(num_bytes, 2, &self.context)?)
+                    } else
+                    {
+                        // minus 6 because 1 for flags, 4 for chat_mode, 1 for '\0'
+                        (self.read_unsigned_dword()?, data_size(num_bytes, 6, &self.context)?)
+                    };
 
-                    blocks.push(
-                        ReplayBlock::PlayerChat { 
+                    return Ok(
+                        BlockHeader::PlayerChat {
                             player_id,
                             num_bytes,
                             flags,
                             chat_mode,
-                            message,
+                            message_size,
                         }
                     );
                 },
                 0x22 =>
                 {
-                    blocks.push(
-                        ReplayBlock::RandomSeed { 
-                            num_bytes: self.read_unsigned_byte()?, 
-                            unknown: self.read_unsigned_dword()?,
-                        }
-                    );
+                    ReplayBlock::RandomSeed { 
+                        num_bytes: self.read_unsigned_byte()?, 
+                        unknown: self.read_unsigned_dword()?,
+                    }
                 },
                 0x23 =>
                 {
-                    blocks.push(
-                        ReplayBlock::Desync {  
-                            tick_count: self.read_unsigned_dword()?,  
-                            checksum: self.read_unsigned_dword()?, 
-                            remaining_players: self.read_unsigned_byte()?,
-                        }
-                    );
+                    ReplayBlock::Desync {  
+                        tick_count: self.read_unsigned_dword()?,  
+                        checksum: self.read_unsigned_dword()?, 
+                        remaining_players: self.read_unsigned_byte()?,
+                    }
                 },
                 0x2F =>
                 {
-                    blocks.push(
-                        ReplayBlock::ForceGameEndCountdown {  
-                            mode: self.read_unsigned_dword()?,  
-                            time: self.read_unsigned_dword()?,  
-                        }
-                    );
+                    ReplayBlock::ForceGameEndCountdown {  
+                        mode: self.read_unsigned_dword()?,  
+                        time: self.read_unsigned_dword()?,  
+                    }
                 },
-                _ => {},
-            }
+                _ => continue,
+            };
 
-            
-            block_id = self.read_unsigned_byte()?;
+            return Ok(BlockHeader::Fixed(block));
+        }
+    }
+
+    /// The '\0' after a chat message
+    pub(crate) fn read_chat_terminator(&mut self) -> Result<()>
+    {
+        if self.read_unsigned_byte()? != 0x0
+        {
+            bail!(ErrorKind::Parse(ParseError::UnterminatedString { context: self.context.clone() }));
+        }
+
+        Ok(())
+    }
+
+    fn extract_block(&mut self) -> Result<Option<ReplayBlock>>
+    {
+        let block = match self.extract_block_header()?
+        {
+            BlockHeader::End => return Ok(None),
+            BlockHeader::Fixed(block) => block,
+            BlockHeader::Tick { pre_overflow, num_bytes, time_increment, commands_size } =>
+            {
+                let commands = self.extract_commands(commands_size)?;
+
+                if pre_overflow
+                {
+                    ReplayBlock::TickPreOverflow { 
+                        num_bytes,
+                        time_increment,
+                        commands,
+                    }
+                } else
+                {
+                    ReplayBlock::Tick { 
+                        num_bytes,
+                        time_increment,
+                        commands,
+                    }
+                }
+            },
+            BlockHeader::PlayerChat { player_id, num_bytes, flags, chat_mode, message_size } =>
+            {
+                let message = self.read_bytes(message_size)?;
+                let (message, raw_message) = self.decode_string(message)?;
+                self.read_chat_terminator()?;
+
+                ReplayBlock::PlayerChat { 
+                    player_id,
+                    num_bytes,
+                    flags,
+                    chat_mode,
+                    message,
+                    raw_message,
+                }
+            },
+        };
+
+        Ok(Some(block))
+    }
+
+    /// Same as `extract_block` except the ticks & chat of every block that isn't a `ReplayBlock::LeaveGame` are skipped over without being decoded
+    fn next_leave_game(&mut self) -> Result<Option<ReplayBlock>>
+    {
+        loop
+        {
+            match self.extract_block_header()?
+            {
+                BlockHeader::End => return Ok(None),
+                BlockHeader::Fixed(block @ ReplayBlock::LeaveGame { .. }) => return Ok(Some(block)),
+                BlockHeader::Fixed(_) => {},
+                BlockHeader::Tick { commands_size, .. } => self.skip_bytes(commands_size)?,
+                // plus 1 for '\0'
+                BlockHeader::PlayerChat { message_size, .. } => self.skip_bytes(message_size + 1)?,
+            };
         }
-        
-        Ok(blocks)
     }
 
     fn extract_commands(&mut self, commands_size: usize) -> Result<Vec<Command>>
@@ -483,9 +1184,11 @@ impl<R: Read> ReplayStream<R>
         let mut bytes_read = 0;
         while bytes_read < commands_size
         {
+            self.context.action_id = None;
             let player_id = self.read_unsigned_byte()?;
+            self.context.player_id = Some(player_id);
             let num_bytes = self.read_unsigned_word()?;
-            let actions = self.extract_actions(num_bytes as usize)?;
+            let actions = self.extract_actions(player_id, num_bytes as usize)?;
 
             commands.push( Command {
                 player_id,
@@ -498,844 +1201,1123 @@ impl<R: Read> ReplayStream<R>
         Ok(commands) 
     }
 
-    fn extract_actions(&mut self, actions_size: usize) -> Result<Vec<Action>>
+    pub(crate) fn extract_actions(&mut self, player_id: u8, actions_size: usize) -> Result<Vec<Action>>
     {
         let mut actions = Vec::new();
 
-        let mut bytes_read = 0;
-        while bytes_read < actions_size
+        let start = self.context.offset;
+        while ((self.context.offset - start) as usize) < actions_size
         {
             let action_id = self.read_unsigned_byte()?;
-            bytes_read = bytes_read + 1; 
+            self.context.action_id = Some(action_id);
 
-            match action_id
-            {
-                0x01 =>
-                { 
-                    actions.push(
-                        Action::PauseGame {}
-                    );
-                },
-                0x02 =>
-                { 
-                    actions.push(
-                        Action::ResumeGame {}
-                    );
-                },
-                0x03 =>
-                { 
-                    let speed = GameSpeed::from_u8(self.read_unsigned_byte()?)?;
-                    bytes_read = bytes_read + 1;
+            actions.push(self.extract_action(player_id, action_id, start, actions_size)?);
+        }
 
-                    actions.push(
-                        Action::SetGameSpeed 
-                        {
-                            speed,
-                        }
-                    );
-                },
-                0x04 =>
-                { 
-                    actions.push(
-                        Action::IncreaseGameSpeed {}
-                    );
-                },
-                0x05 =>
-                { 
-                    actions.push(
-                        Action::DecreaseGameSpeed {}
-                    );
-                },
-                0x06 =>
-                { 
-                    let game_name = self.read_null_terminated_string()?;
+        Ok(actions)
+    }
 
-                    bytes_read = bytes_read + game_name.len() + NULL_BYTE_LENGTH;
-                    actions.push(
-                        Action::SaveGame 
-                        {
-                            game_name,
-                        }
-                    );
-                },
-                0x07 =>
-                { 
-                    bytes_read = bytes_read + 4;
+    /// Decodes what follows `action_id`, `start` is the offset of the command's first action and `actions_size` how long they all are
+    pub(crate) fn extract_action(&mut self, player_id: u8, action_id: u8, start: u64, actions_size: usize) -> Result<Action>
+    {
+        let action = match action_id
+        {
+            0x01 =>
+            { 
+                Action::PauseGame {}
+            },
+            0x02 =>
+            { 
+                Action::ResumeGame {}
+            },
+            0x03 =>
+            { 
+                let speed = GameSpeed::from_u8(self.read_unsigned_byte()?)?;
+                Action::SetGameSpeed 
+                {
+                    speed,
+                }
+            },
+            0x04 =>
+            { 
+                Action::IncreaseGameSpeed {}
+            },
+            0x05 =>
+            { 
+                Action::DecreaseGameSpeed {}
+            },
+            0x06 =>
+            { 
+                let game_name = self.read_null_terminated_string()?;
+
+                Action::SaveGame 
+                {
+                    game_name,
+                }
+            },
+            0x07 =>
+            { 
+                Action::SaveGameFinish 
+                {
+                    unknown: self.read_unsigned_dword()?,
+                }
+            },
+            0x10 =>
+            { 
+                let (flags, order_id, unknown) = self.extract_order()?;
 
-                    actions.push(
-                        Action::SaveGameFinish 
-                        {
-                            unknown: self.read_unsigned_dword()?,
-                        }
-                    );
-                },
-                0x10 =>
-                { 
-                    let flags = OrderType::from_u16(self.read_unsigned_word()?)?;
-                    let order_id = self.read_unsigned_dword()?;
-                    let unknown = self.extract_game_object()?;
+                Action::SelfOrder 
+                {
+                    flags,
+                    order_id,
+                    unknown,
+                }
+            },
+            0x11 =>
+            {
+                let (flags, order_id, unknown) = self.extract_order()?;
+                let x = self.read_float32()?;
+                let y = self.read_float32()?;
+
+                Action::PointOrder 
+                {
+                    flags,
+                    order_id,
+                    unknown,
+                    x,
+                    y,
+                }
+            },
+            0x12 =>
+            { 
+                let (flags, order_id, unknown) = self.extract_order()?;
+                let x = self.read_float32()?;
+                let y = self.read_float32()?;
+                let target = self.extract_game_object()?;
+
+                Action::ObjectOrder 
+                {
+                    flags,
+                    order_id,
+                    unknown,
+                    x,
+                    y,
+                    target,
+                }
+            },
+            0x13 =>
+            { 
+                let (flags, order_id, unknown) = self.extract_order()?;
+                let x = self.read_float32()?;
+                let y = self.read_float32()?;
+                let receiver = self.extract_game_object()?;
+                let item = self.extract_game_object()?;
 
-                    bytes_read = bytes_read + 2 + 3*4;
 
-                    actions.push(
-                        Action::SelfOrder 
-                        {
-                            flags,
-                            order_id,
-                            unknown,
-                        }
-                    );
-                },
-                0x11 =>
+                Action::DropOrGiveItem 
                 {
-                    let flags = OrderType::from_u16(self.read_unsigned_word()?)?;
-                    let order_id = self.read_unsigned_dword()?;
-                    let unknown = self.extract_game_object()?;
-                    let x = self.read_float32()?;
-                    let y = self.read_float32()?;
+                    flags,
+                    order_id,
+                    unknown,
+                    x,
+                    y,
+                    receiver,
+                    item,
+                }
+            },
+            0x14 =>
+            { 
+                let (flags, order_id, unknown) = self.extract_order()?;
+                let x = self.read_float32()?;
+                let y = self.read_float32()?;
+                let target_type = self.read_unsigned_dword()?;
+                let target_flags = self.read_unsigned_qword()?;
+                let target_owner = self.read_unsigned_byte()?;
+                let target_x = self.read_float32()?;
+                let target_y = self.read_float32()?;
+
+                Action::FogObjectOrder 
+                {
+                    flags,
+                    order_id,
+                    unknown,
+                    x,
+                    y,
+                    target_type,
+                    target_flags,
+                    target_owner,
+                    target_x,
+                    target_y, 
+                }
+            },
+            0x16 =>
+            { 
+                let select_mode = SelectionOperation::from_u8(self.read_unsigned_byte()?)?;
+                let num_targets = self.read_unsigned_word()?;
+                let mut targets = Vec::with_capacity(num_targets as usize);
+                for _ in 0..num_targets
+                {
+                    targets.push(self.extract_game_object()?);
+                }
 
-                    bytes_read = bytes_read + 2 + 5*4;
+                Action::ChangeSelection 
+                {
+                    select_mode,
+                    targets,
+                }
+            },
+            0x17 =>
+            { 
+                let group_number = self.read_unsigned_byte()?;
+                let num_targets = self.read_unsigned_word()?;
+                let mut targets = Vec::with_capacity(num_targets as usize);
+                for _ in 0..num_targets
+                {
+                    targets.push(self.extract_game_object()?);
+                }
 
-                    actions.push(
-                        Action::PointOrder 
-                        {
-                            flags,
-                            order_id,
-                            unknown,
-                            x,
-                            y,
-                        }
-                    );
-                },
-                0x12 =>
+                Action::AssignGroup 
+                {
+                    group_number,
+                    targets,
+                }
+            },
+            0x18 =>
+            { 
+                Action::SelectGroup 
+                {
+                    group_number: self.read_unsigned_byte()?, 
+                    unknown: self.read_unsigned_byte()?,  
+                }
+            },
+            0x19 if self.version_number < SELECT_SUBGROUP_ITEM_VERSION_NUMBER =>
+            { 
+                Action::SelectSubGroupIndex 
+                {
+                    subgroup: self.read_unsigned_byte()?, 
+                }
+            },
+            0x19 =>
+            { 
+                Action::SelectSubGroup 
+                {
+                    item_id: self.read_unsigned_dword()?, 
+                    target: self.extract_game_object()?, 
+                }
+            },
+            0x1A =>
+            { 
+                Action::PreSubSelection {}
+            },
+            0x1B =>
+            { 
+                Action::TriggerSelectionEvent 
+                {
+                    operation: SelectionOperation::from_u8(self.read_unsigned_byte()?)?,
+                    target: self.extract_game_object()?,
+                }
+            },
+            0x1C =>
+            { 
+                Action::SelectGroundItem 
+                {
+                    flags: self.read_unsigned_byte()?, 
+                    target: self.extract_game_object()?,
+                }
+            },
+            0x1D =>
+            { 
+                Action::CancelHeroRevival 
                 { 
-                    let flags = OrderType::from_u16(self.read_unsigned_word()?)?;
-                    let order_id = self.read_unsigned_dword()?;
-                    let unknown =self.extract_game_object()?;
-                    let x = self.read_float32()?;
-                    let y = self.read_float32()?;
-                    let target = self.extract_game_object()?;
-
-                    bytes_read = bytes_read + 2 + 7*4;
-
-                    actions.push(
-                        Action::ObjectOrder 
-                        {
-                            flags,
-                            order_id,
-                            unknown,
-                            x,
-                            y,
-                            target,
-                        }
-                    );
-                },
-                0x13 =>
+                    target: self.extract_game_object()?, 
+                }
+            },
+            0x1E =>
+            { 
+                Action::CancelUnitInQueue 
                 { 
-                    let flags = OrderType::from_u16(self.read_unsigned_word()?)?;
-                    let order_id = self.read_unsigned_dword()?; 
-                    let unknown = self.extract_game_object()?;
-                    let x = self.read_float32()?;
-                    let y = self.read_float32()?;
-                    let receiver = self.extract_game_object()?;
-                    let item = self.extract_game_object()?;
- 
+                    slot_index: self.read_unsigned_byte()?,  
+                    unit_id: self.read_unsigned_dword()?,  
+                }
+            },
+            0x21 =>
+            { 
+                Action::Unknown21 
+                { 
+                    unknown_a: self.read_unsigned_dword()?,  
+                    unknown_b: self.read_unsigned_dword()?,  
+                }
+            },
+            0x20 =>
+            { 
+                Action::CheatTheDudeAbides {}
+            },
+            0x22 =>
+            { 
+                Action::CheatSomebodySetUpUsTheBomb {}
+            },
+            0x23 =>
+            { 
+                Action::CheatWarpTen {}
+            },
+            0x24 =>
+            { 
+                Action::CheatIocainePowder {}
+            },
+            0x25 =>
+            { 
+                Action::CheatPointBreak {}
+            },
+            0x26 =>
+            { 
+                Action::CheatWhosYourDaddy {}
+            },
+            0x27 =>
+            { 
+                Action::CheatKeyserSoze {
+                    unknown: self.read_unsigned_byte()?,
+                    gold: self.read_signed_dword()?,
+                }
+            },
+            0x28 =>
+            { 
+                Action::CheatLeafItToMe {
+                    unknown: self.read_unsigned_byte()?,
+                    lumber: self.read_signed_dword()?,
+                }
+            },
+            0x29 =>
+            { 
+                Action::CheatThereIsNoSpoon {}
+            },
+            0x2A=>
+            { 
+                Action::CheatStrengthAndHonor {}
+            },
+            0x2B=>
+            { 
+                Action::CheatItVexesMe {}
+            },
+            0x2C=>
+            { 
+                Action::CheatWhoIsJohnGalt {}
+            },
+            0x2D =>
+            { 
+                Action::CheatGreedIsGood {
+                    unknown: self.read_unsigned_byte()?,
+                    resources: self.read_signed_dword()?,
+                }
+            },
+            0x2E =>
+            { 
+                Action::CheatDaylightSavings {
+                    time: self.read_float32()?,
+                }
+            },
+            0x2F =>
+            { 
+                Action::CheatISeeDeadPeople {}
+            },
+            0x30 =>
+            { 
+                Action::CheatSynergy {}
+            },
+            0x31 =>
+            { 
+                Action::CheatSharpAndShiny {}
+            },
+            0x32 =>
+            { 
+                Action::CheatAllYourBaseAreBelongToUs {}
+            },
+            0x50 =>
+            { 
+                Action::ChangeAlly {
+                    player_id: self.read_unsigned_byte()?,
+                    flags: AllianceType::from_u32(self.read_unsigned_dword()?)?, 
+                }
+            },
+            0x51 =>
+            { 
+                Action::TransferResources {
+                    player_id: self.read_unsigned_byte()?,
+                    gold_transfered: self.read_signed_dword()?,
+                    lumber_transfered: self.read_signed_dword()?,
+                }
+            },
+            0x60 =>
+            { 
+                let event = self.extract_game_object()?; 
+                let (message, raw_message) = self.read_raw_string()?;
+
+                Action::MapTriggerChat { 
+                    event,
+                    message,
+                    raw_message,
+                }
+            },
+            0x61 =>
+            { 
+                Action::Esc {}
+            },
+            0x62 =>
+            { 
+                let thread = self.extract_game_object()?;
+                let wait_count = self.read_unsigned_dword()?;
+
+                Action::TriggerSleepOrSyncFinished { 
+                    thread,
+                    wait_count,
+                }
+            },
+            0x63 =>
+            { 
+                let thread = self.extract_game_object()?;
 
-                    bytes_read = bytes_read + 2 + 9*4;
+                Action::TriggerSyncReady { 
+                    thread,
+                }
+            },
+            0x64 =>
+            {
+                let trackable = self.extract_game_object()?;
 
-                    actions.push(
-                        Action::DropOrGiveItem 
-                        {
-                            flags,
-                            order_id,
-                            unknown,
-                            x,
-                            y,
-                            receiver,
-                            item,
-                        }
-                    );
-                },
-                0x14 =>
-                { 
-                    let flags = OrderType::from_u16(self.read_unsigned_word()?)?;
-                    let order_id = self.read_unsigned_dword()?; 
-                    let unknown = self.extract_game_object()?;
-                    let x = self.read_float32()?;
-                    let y = self.read_float32()?;
-                    let target_type = self.read_unsigned_dword()?;
-                    let target_flags = self.read_unsigned_qword()?;
-                    let target_owner = self.read_unsigned_byte()?;
-                    let target_x = self.read_float32()?;
-                    let target_y = self.read_float32()?;
- 
-                    bytes_read = bytes_read + 2 + 8*4 + 9;
+                Action::TriggerMouseClickedTrackable { 
+                    trackable,
+                }
+            },
+            0x65 =>
+            {
+                let trackable = self.extract_game_object()?;
 
-                    actions.push(
-                        Action::FogObjectOrder 
-                        {
-                            flags,
-                            order_id,
-                            unknown,
-                            x,
-                            y,
-                            target_type,
-                            target_flags,
-                            target_owner,
-                            target_x,
-                            target_y, 
-                        }
-                    );
-                },
-                0x16 =>
-                { 
-                    let select_mode = SelectionOperation::from_u8(self.read_unsigned_byte()?)?;
-                    let num_targets = self.read_unsigned_word()?;
-                    let mut targets = Vec::with_capacity(num_targets as usize);
-                    for _ in 0..num_targets
-                    {
-                        targets.push(self.extract_game_object()?);
-                    }
+                Action::TriggerMouseTouchedTrackable { 
+                    trackable,
+                }
+            },
+            0x66 =>
+            { 
+                Action::EnterHeroSkillSubMenu {}
+            },
+            0x67 =>
+            { 
+                Action::EnterBuildingSubMenu {}
+            },
+            0x68 =>
+            {
+                Action::MiniMapSignal { 
+                    location_x: self.read_float32()?,
+                    location_y: self.read_float32()?,
+                    duration: self.read_float32()?,  
+                }
+            },
+            0x69 =>
+            {
+                let dialog = self.extract_game_object()?;
+                let button = self.extract_game_object()?;
 
-                    bytes_read = bytes_read + 1 + 2 + (num_targets as usize)*(2*4);
+                Action::DialogButtonClicked { 
+                    dialog,
+                    button,
+                }
+            },
+            0x6A =>
+            {
+                let button = self.extract_game_object()?;
+                let dialog = self.extract_game_object()?;
+                
+                Action::DialogAnyButtonClicked { 
+                    button,
+                    dialog,
+                }
+            },
+            0x6B =>
+            {
+                let file = self.read_null_terminated_string()?;
+                let group = self.read_null_terminated_string()?;
+                let key = self.read_null_terminated_string()?;
+                let value = self.read_signed_dword()?;
+
+                Action::SyncStoredInteger {
+                    file,
+                    group,
+                    key,
+                    value,
+                }
+            },
+            0x6C =>
+            {
+                let file = self.read_null_terminated_string()?;
+                let group = self.read_null_terminated_string()?;
+                let key = self.read_null_terminated_string()?;
+
+                Action::SyncStoredFloat {
+                    file,
+                    group,
+                    key,
+                    value: self.read_float32()?,
+                }
+            },
+            0x6D =>
+            {
+                let file = self.read_null_terminated_string()?;
+                let group = self.read_null_terminated_string()?;
+                let key = self.read_null_terminated_string()?;
+
+                Action::SyncStoredBoolean {
+                    file,
+                    group,
+                    key,
+                    value: self.read_unsigned_dword()?,
+                }
+            },
+            0x6E =>
+            {
+                let file = self.read_null_terminated_string()?;
+                let group = self.read_null_terminated_string()?;
+                let key = self.read_null_terminated_string()?;
+
+                let StoredUnit {
+                    unit_type,
+                    inventory,
+                    experience,
+                    level_ups,
+                    skill_points,
+                    proper_name_index,
+                    unknown1,
+                    base_strength,
+                    bonus_strength_per_level,
+                    base_agility,
+                    bonus_move_speed,
+                    bonus_attack_speed,
+                    bonus_agility_per_level,
+                    base_intelligence,
+                    bonus_intelligence_per_level,
+                    abilities,
+                    bonus_health,
+                    bonus_mana,
+                    sight_radius_day,
+                    unknown2,
+                    unknown3,
+                    unknown4,
+                    unknown5,
+                    hotkey_flags,
+                } = self.extract_stored_unit()?;
+
+                Action::SyncStoredUnit {
+                    file,
+                    group,
+                    key,
+                    unit_type,
+                    inventory,
+                    experience,
+                    level_ups,
+                    skill_points,
+                    proper_name_index,
+                    unknown1,
+                    base_strength,
+                    bonus_strength_per_level,
+                    base_agility,
+                    bonus_move_speed,
+                    bonus_attack_speed,
+                    bonus_agility_per_level,
+                    base_intelligence,
+                    bonus_intelligence_per_level,
+                    abilities,
+                    bonus_health,
+                    bonus_mana,
+                    sight_radius_day,
+                    unknown2,
+                    unknown3,
+                    unknown4,
+                    unknown5,
+                    hotkey_flags,
+                }
+            },
+            0x6F =>
+            {
+                let file = self.read_null_terminated_string()?;
+                let group = self.read_null_terminated_string()?;
+                let key = self.read_null_terminated_string()?;
+                let value = self.read_null_terminated_string()?;
+
+                Action::SyncStoredString {
+                    file,
+                    group,
+                    key,
+                    value,
+                }
+            },
+            0x70 =>
+            {
+                let file = self.read_null_terminated_string()?;
+                let group = self.read_null_terminated_string()?;
+                let key = self.read_null_terminated_string()?;
+
+                Action::SyncEmptyInteger {
+                    file,
+                    group,
+                    key,
+                }
+            },
+            0x71 =>
+            {
+                let file = self.read_null_terminated_string()?;
+                let group = self.read_null_terminated_string()?;
+                let key = self.read_null_terminated_string()?; 
+
+                Action::SyncEmptyString {
+                    file,
+                    group,
+                    key,
+                }
+            },
+            0x72 =>
+            {
+                let file = self.read_null_terminated_string()?;
+                let group = self.read_null_terminated_string()?;
+                let key = self.read_null_terminated_string()?;
+
+                Action::SyncEmptyBoolean {
+                    file,
+                    group,
+                    key,
+                }
+            },
+            0x73 =>
+            {
+                let file = self.read_null_terminated_string()?;
+                let group = self.read_null_terminated_string()?;
+                let key = self.read_null_terminated_string()?;
+
+                Action::SyncEmptyUnit {
+                    file,
+                    group,
+                    key,
+                }
+            },
+            0x74 =>
+            {
+                let file = self.read_null_terminated_string()?;
+                let group = self.read_null_terminated_string()?;
+                let key = self.read_null_terminated_string()?;
+
+                Action::SyncEmptyFloat {
+                    file,
+                    group,
+                    key,
+                }
+            },
+            0x75 =>
+            {
+                Action::TriggerArrow {
+                    key: ArrowKeyEvent::from_u8(self.read_unsigned_byte()?)?,
+                }
+            },
+            0x76 if self.is_reforged() =>
+            {
+                Action::MouseAction {
+                    event: self.read_unsigned_byte()?,
+                    x: self.read_float32()?,
+                    y: self.read_float32()?,
+                    button: self.read_unsigned_byte()?,
+                }
+            },
+            0x77 if self.is_reforged() =>
+            {
+                let command_id = self.read_unsigned_dword()?;
+                let data = self.read_unsigned_dword()?;
+                let buffer_size = self.read_unsigned_dword()?;
+                let buffer = self.read_bytes(buffer_size as usize)?;
+
+                Action::W3Api {
+                    command_id,
+                    data,
+                    buffer,
+                }
+            },
+            0x78 if self.is_reforged() =>
+            {
+                let identifier = self.read_null_terminated_string()?;
+                let value = self.read_null_terminated_string()?;
+                let unknown = self.read_unsigned_dword()?;
+
+                Action::BlzSync {
+                    identifier,
+                    value,
+                    unknown,
+                }
+            },
+            0x79 if self.is_reforged() =>
+            {
+                let unknown_a = self.read_unsigned_dword()?;
+                let unknown_b = self.read_unsigned_dword()?;
+                let event_id = self.read_unsigned_dword()?;
+                let value = self.read_float32()?;
+                let text = self.read_null_terminated_string()?;
+
+                Action::CommandFrame {
+                    unknown_a,
+                    unknown_b,
+                    event_id,
+                    value,
+                    text,
+                }
+            },
+            0x7A if self.is_reforged() =>
+            {
+                Action::Unknown7A {
+                    unknown: self.read_bytes(20)?,
+                }
+            },
+            0x7B if self.is_reforged() =>
+            {
+                Action::Unknown7B {
+                    unknown: self.read_bytes(16)?,
+                }
+            },
+            0xA0 if self.is_reforged() =>
+            {
+                Action::UnknownA0 {
+                    unknown: self.read_bytes(14)?,
+                }
+            },
+            0xA1 if self.is_reforged() =>
+            {
+                Action::UnknownA1 {
+                    unknown: self.read_bytes(9)?,
+                }
+            },
+            _ if self.options.mode == ParseMode::Lenient =>
+            {
+                /* There's no way to know how long the action is so skip the rest of the command, the next command is where parsing picks back up */
+                let bytes_read = (self.context.offset - start) as usize;
+                let bytes = self.read_bytes(actions_size.saturating_sub(bytes_read))?;
+
+                self.report(ParseWarning::UnknownAction { player_id, action_id, skipped_bytes: bytes.len() + 1 })?;
+                Action::Unknown {
+                    id: action_id,
+                    bytes,
+                }
+            },
+            _ => bail!(ErrorKind::Parse(ParseError::UnknownAction { context: self.context.clone() })),
+        };
 
-                    actions.push(
-                        Action::ChangeSelection 
-                        {
-                            select_mode,
-                            targets,
-                        }
-                    );
-                },
-                0x17 =>
-                { 
-                    let group_number = self.read_unsigned_byte()?;
-                    let num_targets = self.read_unsigned_word()?;
-                    let mut targets = Vec::with_capacity(num_targets as usize);
-                    for _ in 0..num_targets
-                    {
-                        targets.push(self.extract_game_object()?);
-                    }
+        Ok(action)
+    }
 
-                    bytes_read = bytes_read + 1 + 2 + (num_targets as usize)*(2*4);
+    /// The rest of a 0x6E once its file, group & key have been read
+    fn extract_stored_unit(&mut self) -> Result<StoredUnit>
+    {
+        let unit_type = self.read_unsigned_dword()?;
+        let inventory = self.extract_unit_inventory()?;
+        let experience = self.read_unsigned_dword()?;
+        let level_ups = self.read_unsigned_dword()?;
+        let skill_points = self.read_unsigned_dword()?;
+        let proper_name_index = self.read_unsigned_word()?;
+        let unknown1 = self.read_unsigned_word()?;
+        let base_strength = self.read_unsigned_dword()?;
+        let bonus_strength_per_level = self.read_float32()?;
+        let base_agility = self.read_unsigned_dword()?;
+        let bonus_move_speed = self.read_float32()?;
+        let bonus_attack_speed = self.read_float32()?;
+        let bonus_agility_per_level = self.read_float32()?;
+        let base_intelligence = self.read_unsigned_dword()?;
+        let bonus_intelligence_per_level = self.read_float32()?;
+        let abilities = self.extract_unit_abilites()?;
+        let bonus_health = self.read_float32()?;
+        let bonus_mana = self.read_float32()?;
+        let sight_radius_day = self.read_float32()?;
+        let unknown2 = self.read_unsigned_dword()?;
+        let unknown3 = self.read_unsigned_dword()?;
+        let unknown4 = self.read_unsigned_dword()?;
+        let unknown5 = self.read_unsigned_dword()?;
+        let hotkey_flags = self.read_unsigned_word()?;
+
+        Ok(StoredUnit {
+            unit_type,
+            inventory,
+            experience,
+            level_ups,
+            skill_points,
+            proper_name_index,
+            unknown1,
+            base_strength,
+            bonus_strength_per_level,
+            base_agility,
+            bonus_move_speed,
+            bonus_attack_speed,
+            bonus_agility_per_level,
+            base_intelligence,
+            bonus_intelligence_per_level,
+            abilities,
+            bonus_health,
+            bonus_mana,
+            sight_radius_day,
+            unknown2,
+            unknown3,
+            unknown4,
+            unknown5,
+            hotkey_flags,
+        })
+    }
 
-                    actions.push(
-                        Action::AssignGroup 
-                        {
-                            group_number,
-                            targets,
-                        }
-                    );
-                },
-                0x18 =>
-                { 
-                    bytes_read = bytes_read + 2*1;
+    /// Reads the fields shared by every order (0x10 - 0x14)
+    /// 
+    /// # Return
+    /// * The flags, order id and unknown object
+    fn extract_order(&mut self) -> Result<(Vec<OrderType>, u32, GameObject)>
+    {
+        let flags = if self.version_number < ORDER_FLAGS_WORD_VERSION_NUMBER
+        {
+            OrderType::from_u16(self.read_unsigned_byte()? as u16)?
+        } else 
+        {
+            OrderType::from_u16(self.read_unsigned_word()?)?
+        };
 
-                    actions.push(
-                        Action::SelectGroup 
-                        {
-                            group_number: self.read_unsigned_byte()?, 
-                            unknown: self.read_unsigned_byte()?,  
-                        }
-                    );
-                },
-                0x19 =>
-                { 
-                    bytes_read = bytes_read + 3*4;
+        let order_id = self.read_unsigned_dword()?;
 
-                    actions.push(
-                        Action::SelectSubGroup 
-                        {
-                            item_id: self.read_unsigned_dword()?, 
-                            target: self.extract_game_object()?, 
-                        }
-                    );
-                },
-                0x1A =>
-                { 
-                    actions.push(
-                        Action::PreSubSelection {}
-                    );
-                },
-                0x1B =>
-                { 
-                    bytes_read = bytes_read + 1 + 2*4;
+        /* Didn't exist before 1.07, filled with -1 which is what later patches usually store */
+        let unknown = if self.version_number < ORDER_OBJECT_VERSION_NUMBER
+        {
+            GameObject::new(0xFFFF_FFFF, 0xFFFF_FFFF)
+        } else 
+        {
+            self.extract_game_object()?
+        };
 
-                    actions.push(
-                        Action::TriggerSelectionEvent 
-                        {
-                            operation: SelectionOperation::from_u8(self.read_unsigned_byte()?)?,
-                            target: self.extract_game_object()?,
-                        }
-                    );
-                },
-                0x1C =>
-                { 
-                    bytes_read = bytes_read + 1 + 2*4;
+        Ok((flags, order_id, unknown))
+    }
 
-                    actions.push(
-                        Action::SelectGroundItem 
-                        {
-                            flags: self.read_unsigned_byte()?, 
-                            target: self.extract_game_object()?,
-                        }
-                    );
-                },
-                0x1D =>
-                { 
-                    bytes_read = bytes_read + 2*4;
+    fn extract_game_object(&mut self) -> Result<GameObject>
+    {
+        Ok(GameObject::new(self.read_unsigned_dword()?, self.read_unsigned_dword()?)) 
+    }
 
-                    actions.push(
-                        Action::CancelHeroRevival 
-                        { 
-                            target: self.extract_game_object()?, 
-                        }
-                    );
-                },
-                0x1E =>
-                { 
-                    bytes_read = bytes_read + 1 + 4;
+}
 
-                    actions.push(
-                        Action::CancelUnitInQueue 
-                        { 
-                            slot_index: self.read_unsigned_byte()?,  
-                            unit_id: self.read_unsigned_dword()?,  
-                        }
-                    );
-                },
-                0x21 =>
-                { 
-                    bytes_read = bytes_read + 2*4;
+impl<'a> ReplayStream<&'a [u8]>
+{
+    /// A stream over data that has already been decompressed, `context` says where `data` came from
+    pub(crate) fn from_slice(data: &'a [u8], options: ParseOptions, version_number: u32, language_id: Option<u32>, context: ParseContext) -> ReplayStream<&'a [u8]>
+    {
+        let mut stream = ReplayStream::from_file(data, options, version_number);
+        stream.decompressed = true;
+        stream.language_id = language_id;
+        stream.context = context;
 
-                    actions.push(
-                        Action::Unknown21 
-                        { 
-                            unknown_a: self.read_unsigned_dword()?,  
-                            unknown_b: self.read_unsigned_dword()?,  
-                        }
-                    );
-                },
-                0x20 =>
-                { 
-                    actions.push(
-                        Action::CheatTheDudeAbides {}
-                    );
-                },
-                0x22 =>
-                { 
-                    actions.push(
-                        Action::CheatSomebodySetUpUsTheBomb {}
-                    );
-                },
-                0x23 =>
-                { 
-                    actions.push(
-                        Action::CheatWarpTen {}
-                    );
-                },
-                0x24 =>
-                { 
-                    actions.push(
-                        Action::CheatIocainePowder {}
-                    );
-                },
-                0x25 =>
-                { 
-                    actions.push(
-                        Action::CheatPointBreak {}
-                    );
-                },
-                0x26 =>
-                { 
-                    actions.push(
-                        Action::CheatWhosYourDaddy {}
-                    );
-                },
-                0x27 =>
-                { 
-                    bytes_read = bytes_read + 1 + 4;
+        stream
+    }
+
+    pub(crate) fn is_empty(&self) -> bool
+    {
+        self.raw_file.is_empty()
+    }
+
+    /// Same as `extract_block` but chat messages & commands borrow from the slice
+    pub(crate) fn extract_block_view(&mut self) -> Result<Option<ReplayBlockView<'a>>>
+    {
+        let block = match self.extract_block_header()?
+        {
+            BlockHeader::End => return Ok(None),
+            BlockHeader::Fixed(block) => ReplayBlockView::Other(block),
+            BlockHeader::Tick { pre_overflow, num_bytes, time_increment, commands_size } =>
+            {
+                let commands = self.extract_command_views(commands_size)?;
 
-                    actions.push(
-                        Action::CheatKeyserSoze {
-                            unknown: self.read_unsigned_byte()?,
-                            gold: self.read_signed_dword()?,
-                        }
-                    );
-                },
-                0x28 =>
-                { 
-                    bytes_read = bytes_read + 1 + 4;
+                if pre_overflow
+                {
+                    ReplayBlockView::TickPreOverflow { num_bytes, time_increment, commands }
+                } else
+                {
+                    ReplayBlockView::Tick { num_bytes, time_increment, commands }
+                }
+            },
+            BlockHeader::PlayerChat { player_id, num_bytes, flags, chat_mode, message_size } =>
+            {
+                let raw_message = self.borrow_bytes(message_size)?;
+                let message = self.decode_str(raw_message)?;
+                self.read_chat_terminator()?;
 
-                    actions.push(
-                        Action::CheatLeafItToMe {
-                            unknown: self.read_unsigned_byte()?,
-                            lumber: self.read_signed_dword()?,
-                        }
-                    );
-                },
-                0x29 =>
-                { 
-                    actions.push(
-                        Action::CheatThereIsNoSpoon {}
-                    );
-                },
-                0x2A=>
-                { 
-                    actions.push(
-                        Action::CheatStrengthAndHonor {}
-                    );
-                },
-                0x2B=>
-                { 
-                    actions.push(
-                        Action::CheatItVexesMe {}
-                    );
-                },
-                0x2C=>
-                { 
-                    actions.push(
-                        Action::CheatWhoIsJohnGalt {}
-                    );
-                },
-                0x2D =>
-                { 
-                    bytes_read = bytes_read + 1 + 4;
+                ReplayBlockView::PlayerChat { player_id, num_bytes, flags, chat_mode, message, raw_message }
+            },
+        };
 
-                    actions.push(
-                        Action::CheatGreedIsGood {
-                            unknown: self.read_unsigned_byte()?,
-                            resources: self.read_signed_dword()?,
-                        }
-                    );
-                },
-                0x2E =>
-                { 
-                    bytes_read = bytes_read + 4;
+        Ok(Some(block))
+    }
 
-                    actions.push(
-                        Action::CheatDaylightSavings {
-                            time: self.read_float32()?,
-                        }
-                    );
-                },
-                0x2F =>
-                { 
-                    actions.push(
-                        Action::CheatISeeDeadPeople {}
-                    );
-                },
-                0x30 =>
-                { 
-                    actions.push(
-                        Action::CheatSynergy {}
-                    );
-                },
-                0x31 =>
-                { 
-                    actions.push(
-                        Action::CheatSharpAndShiny {}
-                    );
-                },
-                0x32 =>
-                { 
-                    actions.push(
-                        Action::CheatAllYourBaseAreBelongToUs {}
-                    );
-                },
-                0x50 =>
-                { 
-                    bytes_read = bytes_read + 1 + 4;
+    /// Same as `extract_commands` but the actions are left encoded until `CommandView::actions` is called
+    fn extract_command_views(&mut self, commands_size: usize) -> Result<Vec<CommandView<'a>>>
+    {
+        let mut commands = Vec::new();
 
-                    actions.push(
-                        Action::ChangeAlly {
-                            player_id: self.read_unsigned_byte()?,
-                            flags: AllianceType::from_u32(self.read_unsigned_dword()?)?, 
-                        }
-                    );
-                },
-                0x51 =>
-                { 
-                    bytes_read = bytes_read + 1 + 4 + 4;
+        let mut bytes_read = 0;
+        while bytes_read < commands_size
+        {
+            self.context.action_id = None;
+            let player_id = self.read_unsigned_byte()?;
+            self.context.player_id = Some(player_id);
+            let num_bytes = self.read_unsigned_word()?;
 
-                    actions.push(
-                        Action::TransferResources {
-                            player_id: self.read_unsigned_byte()?,
-                            gold_transfered: self.read_signed_dword()?,
-                            lumber_transfered: self.read_signed_dword()?,
-                        }
-                    );
-                },
-                0x60 =>
-                { 
-                    let event = self.extract_game_object()?; 
-                    let message = self.read_null_terminated_string()?;
+            /* Where `data` starts */
+            let context = self.context.clone();
+            let data = self.borrow_bytes(num_bytes as usize)?;
 
-                    bytes_read = bytes_read + 2*4 + message.len() + NULL_BYTE_LENGTH;
+            commands.push( CommandView {
+                player_id,
+                num_bytes,
+                data,
 
-                    actions.push(
-                        Action::MapTriggerChat { 
-                            event,
-                            message,
-                        }
-                    );
-                },
-                0x61 =>
-                { 
-                    actions.push(
-                        Action::Esc {}
-                    );
-                },
-                0x62 =>
-                { 
-                    let thread = self.extract_game_object()?;
-                    let wait_count = self.read_unsigned_dword()?;
+                options: self.options,
+                version_number: self.version_number,
+                language_id: self.language_id,
+                context,
+            });
+            bytes_read = bytes_read + 3 + (num_bytes as usize);
+        }
 
-                    bytes_read = bytes_read + 3*4;
+        Ok(commands)
+    }
 
-                    actions.push(
-                        Action::TriggerSleepOrSyncFinished { 
-                            thread,
-                            wait_count,
-                        }
-                    );
-                },
-                0x63 =>
-                { 
-                    let thread = self.extract_game_object()?;
+    /// Same as `read_bytes` but borrowing
+    fn borrow_bytes(&mut self, length: usize) -> Result<&'a [u8]>
+    {
+        let data = self.raw_file;
+        if length > data.len()
+        {
+            bail!(ErrorKind::Parse(ParseError::UnexpectedEnd { context: self.context.clone() }));
+        }
 
-                    bytes_read = bytes_read + 2*4;
+        let (bytes, rest) = data.split_at(length);
+        self.raw_file = rest;
+        self.context.offset = self.context.offset + length as u64;
 
-                    actions.push(
-                        Action::TriggerSyncReady { 
-                            thread,
-                        }
-                    );
-                },
-                0x64 =>
-                {
-                    let trackable = self.extract_game_object()?;
+        Ok(bytes)
+    }
 
-                    bytes_read = bytes_read + 2*4;
+    fn borrow_null_terminated_bytes(&mut self) -> Result<&'a [u8]>
+    {
+        match self.raw_file.iter().position(|&byte| byte == 0b0)
+        {
+            Some(length) =>
+            {
+                let bytes = self.borrow_bytes(length)?;
+                self.skip_bytes(1)?;
 
-                    actions.push(
-                        Action::TriggerMouseClickedTrackable { 
-                            trackable,
-                        }
-                    );
-                },
-                0x65 =>
-                {
-                    let trackable = self.extract_game_object()?;
+                Ok(bytes)
+            },
+            None =>
+            {
+                let length = self.raw_file.len();
+                self.skip_bytes(length)?;
 
-                    bytes_read = bytes_read + 2*4;
+                bail!(ErrorKind::Parse(ParseError::UnexpectedEnd { context: self.context.clone() }));
+            },
+        }
+    }
 
-                    actions.push(
-                        Action::TriggerMouseTouchedTrackable { 
-                            trackable,
-                        }
-                    );
-                },
-                0x66 =>
-                { 
-                    actions.push(
-                        Action::EnterHeroSkillSubMenu {}
-                    );
-                },
-                0x67 =>
-                { 
-                    actions.push(
-                        Action::EnterBuildingSubMenu {}
-                    );
-                },
-                0x68 =>
-                {
-                    bytes_read = bytes_read + 3*4;
+    /// Same as `read_raw_string` but the string is only copied when it wasn't valid UTF-8
+    fn borrow_raw_string(&mut self) -> Result<(Cow<'a, str>, &'a [u8])>
+    {
+        let bytes = self.borrow_null_terminated_bytes()?;
 
-                    actions.push(
-                        Action::MiniMapSignal { 
-                            location_x: self.read_float32()?,
-                            location_y: self.read_float32()?,
-                            duration: self.read_float32()?,  
-                        }
-                    );
-                },
-                0x69 =>
-                {
-                    let dialog = self.extract_game_object()?;
-                    let button = self.extract_game_object()?;
+        Ok((self.decode_str(bytes)?, bytes))
+    }
+
+    /// Same as `decode_string` but borrowing `bytes` when they're valid UTF-8
+    fn decode_str(&self, bytes: &'a [u8]) -> Result<Cow<'a, str>>
+    {
+        match encoding::decode_str(bytes, self.options.string_encoding, self.language_id)
+        {
+            Some(string) => Ok(string),
+            None => bail!(ErrorKind::Parse(ParseError::InvalidUtf8 { context: self.context.clone() })),
+        }
+    }
 
-                    bytes_read = bytes_read + 4*4;
+    fn borrow_null_terminated_string(&mut self) -> Result<Cow<'a, str>>
+    {
+        Ok(self.borrow_raw_string()?.0)
+    }
 
-                    actions.push(
-                        Action::DialogButtonClicked { 
-                            dialog,
-                            button,
-                        }
-                    );
-                },
-                0x6A =>
+    /// Same as `extract_actions` but strings & bytes borrow from the slice
+    pub(crate) fn extract_action_views(&mut self, player_id: u8, actions_size: usize) -> Result<Vec<ActionView<'a>>>
+    {
+        let mut actions = Vec::new();
+
+        let start = self.context.offset;
+        while ((self.context.offset - start) as usize) < actions_size
+        {
+            let action_id = self.read_unsigned_byte()?;
+            self.context.action_id = Some(action_id);
+
+            let action = match action_id
+            {
+                0x06 => ActionView::SaveGame { game_name: self.borrow_null_terminated_string()? },
+                0x60 =>
                 {
-                    let button = self.extract_game_object()?;
-                    let dialog = self.extract_game_object()?;
-                    
-                    bytes_read = bytes_read + 4*4;
-
-                    actions.push(
-                        Action::DialogAnyButtonClicked { 
-                            button,
-                            dialog,
-                        }
-                    );
+                    let event = self.extract_game_object()?;
+                    let (message, raw_message) = self.borrow_raw_string()?;
+
+                    ActionView::MapTriggerChat { event, message, raw_message }
                 },
                 0x6B =>
                 {
-                    let file = self.read_null_terminated_string()?;
-                    let group = self.read_null_terminated_string()?;
-                    let key = self.read_null_terminated_string()?;
-                    let value = self.read_signed_dword()?;
-
-                    bytes_read = bytes_read + 4 + file.len() + group.len() + key.len() + 3*NULL_BYTE_LENGTH;
-
-                    actions.push(
-                        Action::SyncStoredInteger {
-                            file,
-                            group,
-                            key,
-                            value,
-                        }
-                    );
+                    let (file, group, key) = self.borrow_sync_keys()?;
+
+                    ActionView::SyncStoredInteger { file, group, key, value: self.read_signed_dword()? }
                 },
                 0x6C =>
                 {
-                    let file = self.read_null_terminated_string()?;
-                    let group = self.read_null_terminated_string()?;
-                    let key = self.read_null_terminated_string()?;
-
-                    bytes_read = bytes_read + 4 + file.len() + group.len() + key.len() + 3*NULL_BYTE_LENGTH;
-
-                    actions.push(
-                        Action::SyncStoredFloat {
-                            file,
-                            group,
-                            key,
-                            value: self.read_float32()?,
-                        }
-                    );
+                    let (file, group, key) = self.borrow_sync_keys()?;
+
+                    ActionView::SyncStoredFloat { file, group, key, value: self.read_float32()? }
                 },
                 0x6D =>
                 {
-                    let file = self.read_null_terminated_string()?;
-                    let group = self.read_null_terminated_string()?;
-                    let key = self.read_null_terminated_string()?;
-
-                    bytes_read = bytes_read + file.len() + group.len() + key.len() + 3*NULL_BYTE_LENGTH;
-
-                    actions.push(
-                        Action::SyncStoredBoolean {
-                            file,
-                            group,
-                            key,
-                            value: self.read_unsigned_dword()?,
-                        }
-                    );
+                    let (file, group, key) = self.borrow_sync_keys()?;
+
+                    ActionView::SyncStoredBoolean { file, group, key, value: self.read_unsigned_dword()? }
                 },
                 0x6E =>
                 {
-                    let file = self.read_null_terminated_string()?;
-                    let group = self.read_null_terminated_string()?;
-                    let key = self.read_null_terminated_string()?;
-                    let unit_type = self.read_unsigned_dword()?;
-                    let inventory = self.extract_unit_inventory()?;
-                    let experience = self.read_unsigned_dword()?;
-                    let level_ups = self.read_unsigned_dword()?;
-                    let skill_points = self.read_unsigned_dword()?;
-                    let proper_name_index = self.read_unsigned_word()?;
-                    let unknown1 = self.read_unsigned_word()?;
-                    let base_strength = self.read_unsigned_dword()?;
-                    let bonus_strength_per_level = self.read_float32()?;
-                    let base_agility = self.read_unsigned_dword()?;
-                    let bonus_move_speed = self.read_float32()?;
-                    let bonus_attack_speed = self.read_float32()?;
-                    let bonus_agility_per_level = self.read_float32()?;
-                    let base_intelligence = self.read_unsigned_dword()?;
-                    let bonus_intelligence_per_level = self.read_float32()?;
-                    let abilities = self.extract_unit_abilites()?;
-                    let bonus_health = self.read_float32()?;
-                    let bonus_mana = self.read_float32()?;
-                    let sight_radius_day = self.read_float32()?;
-                    let unknown2 = self.read_unsigned_dword()?;
-                    let unknown3 = self.read_unsigned_dword()?;
-                    let unknown4 = self.read_unsigned_dword()?;
-                    let unknown5 = self.read_unsigned_dword()?;
-                    let hotkey_flags = self.read_unsigned_word()?;
-
-                    bytes_read = bytes_read 
-                        + file.len() 
-                        + group.len() 
-                        + key.len() 
-                        + 3*NULL_BYTE_LENGTH
-                        + 4
-                        + inventory.len() * 3*4 + 4
-                        + 3*4
-                        + 2*2
-                        + 8*4
-                        + abilities.len() * 2*4 + 4
-                        + 7*4
-                        + 2;
-
-                    actions.push(
-                        Action::SyncStoredUnit {
-                            file,
-                            group,
-                            key,
-                            unit_type,
-                            inventory,
-                            experience,
-                            level_ups,
-                            skill_points,
-                            proper_name_index,
-                            unknown1,
-                            base_strength,
-                            bonus_strength_per_level,
-                            base_agility,
-                            bonus_move_speed,
-                            bonus_attack_speed,
-                            bonus_agility_per_level,
-                            base_intelligence,
-                            bonus_intelligence_per_level,
-                            abilities,
-                            bonus_health,
-                            bonus_mana,
-                            sight_radius_day,
-                            unknown2,
-                            unknown3,
-                            unknown4,
-                            unknown5,
-                            hotkey_flags,
-                        }
-                    );
+                    let (file, group, key) = self.borrow_sync_keys()?;
+
+                    let StoredUnit {
+                        unit_type,
+                        inventory,
+                        experience,
+                        level_ups,
+                        skill_points,
+                        proper_name_index,
+                        unknown1,
+                        base_strength,
+                        bonus_strength_per_level,
+                        base_agility,
+                        bonus_move_speed,
+                        bonus_attack_speed,
+                        bonus_agility_per_level,
+                        base_intelligence,
+                        bonus_intelligence_per_level,
+                        abilities,
+                        bonus_health,
+                        bonus_mana,
+                        sight_radius_day,
+                        unknown2,
+                        unknown3,
+                        unknown4,
+                        unknown5,
+                        hotkey_flags,
+                    } = self.extract_stored_unit()?;
+
+                    ActionView::SyncStoredUnit {
+                        file,
+                        group,
+                        key,
+                        unit_type,
+                        inventory,
+                        experience,
+                        level_ups,
+                        skill_points,
+                        proper_name_index,
+                        unknown1,
+                        base_strength,
+                        bonus_strength_per_level,
+                        base_agility,
+                        bonus_move_speed,
+                        bonus_attack_speed,
+                        bonus_agility_per_level,
+                        base_intelligence,
+                        bonus_intelligence_per_level,
+                        abilities,
+                        bonus_health,
+                        bonus_mana,
+                        sight_radius_day,
+                        unknown2,
+                        unknown3,
+                        unknown4,
+                        unknown5,
+                        hotkey_flags,
+                    }
                 },
                 0x6F =>
                 {
-                    let file = self.read_null_terminated_string()?;
-                    let group = self.read_null_terminated_string()?;
-                    let key = self.read_null_terminated_string()?;
-                    let value = self.read_null_terminated_string()?;
-
-                    bytes_read = bytes_read + file.len() + group.len() + key.len() + 4*NULL_BYTE_LENGTH;
-
-                    actions.push(
-                        Action::SyncStoredString {
-                            file,
-                            group,
-                            key,
-                            value,
-                        }
-                    );
+                    let (file, group, key) = self.borrow_sync_keys()?;
+
+                    ActionView::SyncStoredString { file, group, key, value: self.borrow_null_terminated_string()? }
                 },
                 0x70 =>
                 {
-                    let file = self.read_null_terminated_string()?;
-                    let group = self.read_null_terminated_string()?;
-                    let key = self.read_null_terminated_string()?;
+                    let (file, group, key) = self.borrow_sync_keys()?;
 
-                    bytes_read = bytes_read + file.len() + group.len() + key.len() + 3*NULL_BYTE_LENGTH;
-
-                    actions.push(
-                        Action::SyncEmptyInteger {
-                            file,
-                            group,
-                            key,
-                        }
-                    );
+                    ActionView::SyncEmptyInteger { file, group, key }
                 },
                 0x71 =>
                 {
-                    let file = self.read_null_terminated_string()?;
-                    let group = self.read_null_terminated_string()?;
-                    let key = self.read_null_terminated_string()?; 
-
-                    bytes_read = bytes_read + file.len() + group.len() + key.len() + 3*NULL_BYTE_LENGTH;
+                    let (file, group, key) = self.borrow_sync_keys()?;
 
-                    actions.push(
-                        Action::SyncEmptyString {
-                            file,
-                            group,
-                            key,
-                        }
-                    );
+                    ActionView::SyncEmptyString { file, group, key }
                 },
                 0x72 =>
                 {
-                    let file = self.read_null_terminated_string()?;
-                    let group = self.read_null_terminated_string()?;
-                    let key = self.read_null_terminated_string()?;
+                    let (file, group, key) = self.borrow_sync_keys()?;
 
-                    bytes_read = bytes_read + file.len() + group.len() + key.len() + 3*NULL_BYTE_LENGTH;
-
-                    actions.push(
-                        Action::SyncEmptyBoolean {
-                            file,
-                            group,
-                            key,
-                        }
-                    );
+                    ActionView::SyncEmptyBoolean { file, group, key }
                 },
                 0x73 =>
                 {
-                    let file = self.read_null_terminated_string()?;
-                    let group = self.read_null_terminated_string()?;
-                    let key = self.read_null_terminated_string()?;
-
-                    bytes_read = bytes_read + file.len() + group.len() + key.len() + 3*NULL_BYTE_LENGTH;
+                    let (file, group, key) = self.borrow_sync_keys()?;
 
-                    actions.push(
-                        Action::SyncEmptyUnit {
-                            file,
-                            group,
-                            key,
-                        }
-                    );
+                    ActionView::SyncEmptyUnit { file, group, key }
                 },
                 0x74 =>
                 {
-                    let file = self.read_null_terminated_string()?;
-                    let group = self.read_null_terminated_string()?;
-                    let key = self.read_null_terminated_string()?;
+                    let (file, group, key) = self.borrow_sync_keys()?;
 
-                    bytes_read = bytes_read + file.len() + group.len() + key.len() + 3*NULL_BYTE_LENGTH;
+                    ActionView::SyncEmptyFloat { file, group, key }
+                },
+                0x77 if self.is_reforged() =>
+                {
+                    let command_id = self.read_unsigned_dword()?;
+                    let data = self.read_unsigned_dword()?;
+                    let buffer_size = self.read_unsigned_dword()?;
 
-                    actions.push(
-                        Action::SyncEmptyFloat {
-                            file,
-                            group,
-                            key,
-                        }
-                    );
+                    ActionView::W3Api { command_id, data, buffer: self.borrow_bytes(buffer_size as usize)? }
                 },
-                0x75 =>
+                0x78 if self.is_reforged() =>
                 {
-                    bytes_read = bytes_read + 1;
+                    let identifier = self.borrow_null_terminated_string()?;
+                    let value = self.borrow_null_terminated_string()?;
 
-                    actions.push(
-                        Action::TriggerArrow {
-                            key: ArrowKeyEvent::from_u8(self.read_unsigned_byte()?)?,
-                        }
-                    );
+                    ActionView::BlzSync { identifier, value, unknown: self.read_unsigned_dword()? }
                 },
-                _ => bail!(format!("Unknown action_id: {}", action_id)),
+                0x79 if self.is_reforged() =>
+                {
+                    let unknown_a = self.read_unsigned_dword()?;
+                    let unknown_b = self.read_unsigned_dword()?;
+                    let event_id = self.read_unsigned_dword()?;
+                    let value = self.read_float32()?;
+
+                    ActionView::CommandFrame { unknown_a, unknown_b, event_id, value, text: self.borrow_null_terminated_string()? }
+                },
+                0x7A if self.is_reforged() => ActionView::Unknown7A { unknown: self.borrow_bytes(20)? },
+                0x7B if self.is_reforged() => ActionView::Unknown7B { unknown: self.borrow_bytes(16)? },
+                0xA0 if self.is_reforged() => ActionView::UnknownA0 { unknown: self.borrow_bytes(14)? },
+                0xA1 if self.is_reforged() => ActionView::UnknownA1 { unknown: self.borrow_bytes(9)? },
+                _ => ActionView::Other(self.extract_action(player_id, action_id, start, actions_size)?),
             };
+
+            actions.push(action);
         }
 
         Ok(actions)
     }
 
-    fn extract_game_object(&mut self) -> Result<GameObject>
+    /// The file, group & key every 0x6B - 0x74 starts with
+    fn borrow_sync_keys(&mut self) -> Result<(Cow<'a, str>, Cow<'a, str>, Cow<'a, str>)>
     {
-        Ok(GameObject::new(self.read_unsigned_dword()?, self.read_unsigned_dword()?)) 
+        Ok((self.borrow_null_terminated_string()?, self.borrow_null_terminated_string()?, self.borrow_null_terminated_string()?))
     }
-
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -1365,14 +2347,18 @@ pub struct Replay
     pub game_header: GameHeader,
 
     pub replay_blocks: Vec<ReplayBlock>,
+
+    /* Problems tolerated while parsing leniently, always empty for strict parsing */
+    #[serde(default)]
+    pub warnings: Vec<ParseWarning>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct ReplayHeader {
-    /* 1 dword */
+    /* 1 dword, `LEGACY_VERSION_STRING` for header_version 0 which doesn't store it */
     pub version_string: String,
 
-    /* 1 dword */
+    /* 1 dword (1 word for header_version 0) */
     pub version_number: u32,
 
     /* 1 word */
@@ -1388,6 +2374,28 @@ pub struct ReplayHeader {
     pub crc32: u32,
 }
 
+impl Replay
+{
+    /// Zeroes `compressed_size` & `replay_header.crc32`, which only describe how the blocks happened to be compressed.
+    /// `write_replay` doesn't compress byte for byte like Warcraft III does so this is how a written replay is compared to the original
+    pub fn without_compression(mut self) -> Replay
+    {
+        self.compressed_size = 0;
+        self.replay_header.crc32 = 0;
+
+        self
+    }
+}
+
+impl ReplayHeader
+{
+    /// Reforged (>= 1.32) changed the compressed block header and added new records & actions
+    pub fn is_reforged(&self) -> bool
+    {
+        self.version_number >= REFORGED_VERSION_NUMBER
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct GameHeader {
     /* 1 dword */
@@ -1396,6 +2404,9 @@ pub struct GameHeader {
     pub replay_saver: PlayerRecord,
 
     pub game_name: String,
+    /* The bytes of `game_name` when they weren't valid UTF-8, see `ParseOptions.string_encoding` */
+    #[serde(default)]
+    pub raw_game_name: Option<Vec<u8>>,
 
     pub encoded_string: Vec<u8>,
     /* 1 dword */
@@ -1407,9 +2418,99 @@ pub struct GameHeader {
 
     pub players: Vec<PlayerRecord>,
 
+    /* 0x39 records, only present in Reforged (>= 1.32) */
+    #[serde(default)]
+    pub reforged_metadata: Vec<ReforgedMetadata>,
+
     pub game_record: GameRecord,
 }
 
+impl GameHeader
+{
+    /// Looks through `replay_saver` & `players` for `player_id`
+    pub fn player(&self, player_id: u8) -> Option<&PlayerRecord>
+    {
+        Some(&self.replay_saver).into_iter()
+            .chain(self.players.iter())
+            .find(|player| player.player_id == player_id)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub enum ReforgedMetadata
+{
+    /* 0x03 */
+    Player(ReforgedPlayerMetadata),
+    /* Subtypes that aren't understood are kept as is */
+    Unknown {
+        /* 1 byte */
+        subtype: u8,
+        /* u32 for data size */
+        data: Vec<u8>,
+    },
+}
+
+/// Protobuf encoded, links a player id with their Battle.net account
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct ReforgedPlayerMetadata {
+    /* field 1 */
+    pub player_id: u32,
+    /* field 2 */
+    pub battle_tag: String,
+    /* field 3 */
+    pub clan: String,
+    /* field 4 */
+    pub portrait: String,
+    /* field 5 */
+    pub team: u32,
+    /* field 6 */
+    pub unknown: String,
+}
+
+impl ReforgedPlayerMetadata
+{
+    fn from_protobuf(data: &[u8]) -> Result<ReforgedPlayerMetadata>
+    {
+        let mut metadata = ReforgedPlayerMetadata {
+            player_id: 0,
+            battle_tag: String::new(),
+            clan: String::new(),
+            portrait: String::new(),
+            team: 0,
+            unknown: String::new(),
+        };
+
+        for (field_number, value) in protobuf::decode_fields(data)?
+        {
+            match (field_number, value)
+            {
+                (1, FieldValue::Varint(value)) => metadata.player_id = value as u32,
+                (2, FieldValue::Bytes(value)) => metadata.battle_tag = String::from_utf8(value)?,
+                (3, FieldValue::Bytes(value)) => metadata.clan = String::from_utf8(value)?,
+                (4, FieldValue::Bytes(value)) => metadata.portrait = String::from_utf8(value)?,
+                (5, FieldValue::Varint(value)) => metadata.team = value as u32,
+                (6, FieldValue::Bytes(value)) => metadata.unknown = String::from_utf8(value)?,
+                (field_number, value) => debug!("Ignoring reforged player field {}: {:?}", field_number, value),
+            }
+        }
+
+        Ok(metadata)
+    }
+
+    pub(crate) fn to_protobuf(&self) -> Vec<u8>
+    {
+        let mut data = Vec::new();
+        protobuf::encode_varint_field(&mut data, 1, self.player_id as u64);
+        protobuf::encode_bytes_field(&mut data, 2, self.battle_tag.as_bytes());
+        protobuf::encode_bytes_field(&mut data, 3, self.clan.as_bytes());
+        protobuf::encode_bytes_field(&mut data, 4, self.portrait.as_bytes());
+        protobuf::encode_varint_field(&mut data, 5, self.team as u64);
+        protobuf::encode_bytes_field(&mut data, 6, self.unknown.as_bytes());
+
+        data
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct PlayerRecord {
     /* 1 byte, always 0x16 */
@@ -1418,9 +2519,15 @@ pub struct PlayerRecord {
     pub player_id: u8,
 
     pub player_name: String,
+    /* The bytes of `player_name` when they weren't valid UTF-8, see `ParseOptions.string_encoding` */
+    #[serde(default)]
+    pub raw_player_name: Option<Vec<u8>>,
     /* 1 byte */
     pub additional_data_size: u8,
     pub additional_data: Vec<u8>,
+    /* 1 dword that follows the records in `GameHeader.players`, None for `GameHeader.replay_saver` */
+    #[serde(default)]
+    pub unknown: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -1518,11 +2625,14 @@ pub enum ReplayBlock
         player_id: u8,
         /* 2 bytes */
         num_bytes: u16,
-        /* 1 byte */
+        /* 1 byte, `LOBBY_CHAT_FLAGS` for messages sent before the game started */
         flags: u8,
-        /* 4 bytes */
+        /* 4 bytes, not stored (so 0) for lobby messages */
         chat_mode: u32,
         message: String,
+        /* The bytes of `message` when they weren't valid UTF-8 */
+        #[serde(default)]
+        raw_message: Option<Vec<u8>>,
     },
     /* 0x22 */
     RandomSeed {
@@ -1569,14 +2679,14 @@ pub enum GameSpeed
 
 impl GameSpeed
 {
-    fn from_u8(byte: u8) -> Result<GameSpeed>
+    pub(crate) fn from_u8(byte: u8) -> Result<GameSpeed>
     {
         match byte
         {
             0x0 => Ok(GameSpeed::Slow),
             0x1 => Ok(GameSpeed::Normal),
             0x2 => Ok(GameSpeed::Fast),
-            _ => bail!(format!("{} is not a GameSpeed", byte)),
+            _ => bail!(ErrorKind::InvalidValue(String::from("GameSpeed"), byte as u64)),
         }
     }
 }
@@ -1638,7 +2748,7 @@ impl SelectionOperation
         { 
             1 => Ok(SelectionOperation::Add),
             2 => Ok(SelectionOperation::Remove),
-            _ => bail!(format!("{} is not a SelectionOperation", byte)),
+            _ => bail!(ErrorKind::InvalidValue(String::from("SelectionOperation"), byte as u64)),
         }
     }
 }
@@ -1720,35 +2830,43 @@ impl ArrowKeyEvent
             5 => Ok(ArrowKeyEvent::ReleasedDownArrow),
             6 => Ok(ArrowKeyEvent::PressedUpArrow),
             7 => Ok(ArrowKeyEvent::ReleasedUpArrow), 
-            _ => bail!(format!("{} is not a ArrowKeyEvent", byte)),
+            _ => bail!(ErrorKind::InvalidValue(String::from("ArrowKeyEvent"), byte as u64)),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Copy, Clone)]
 pub struct GameObject
 {
-    allocated_id: u32,
-    counter_id: u32,
+    /* Index of the object in the game's object table, reused once the object is gone */
+    pub allocated_id: u32,
+    /* Together with `allocated_id` identifies the object for the whole game */
+    pub counter_id: u32,
 }
 
 impl GameObject
 {
-    fn new(allocated_id: u32, counter_id: u32) -> GameObject
+    pub fn new(allocated_id: u32, counter_id: u32) -> GameObject
     {
         GameObject {
             allocated_id,
             counter_id,
         }
     }
+
+    /// Both ids are -1 when there's no object, e.g. the target of an order given to a point
+    pub fn is_none(&self) -> bool
+    {
+        self.allocated_id == 0xFFFF_FFFF && self.counter_id == 0xFFFF_FFFF
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct UnitInventory
 {
-    item: u32,
-    charges: u32,
-    unknown: u32,
+    pub(crate) item: u32,
+    pub(crate) charges: u32,
+    pub(crate) unknown: u32,
 }
 
 impl UnitInventory
@@ -1766,8 +2884,8 @@ impl UnitInventory
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct UnitAbility
 {
-    ability: u32,
-    level: u32, 
+    pub(crate) ability: u32,
+    pub(crate) level: u32, 
 }
 
 impl UnitAbility
@@ -1882,6 +3000,11 @@ pub enum Action
         item_id: u32,
         target: GameObject,
     },
+    /* 0x19 before 1.14b */
+    SelectSubGroupIndex {
+        /* 1 byte */
+        subgroup: u8,
+    },
     /* 0x1A */
     PreSubSelection(),
     /* 0x1B */
@@ -1983,6 +3106,9 @@ pub enum Action
     MapTriggerChat {
         event: GameObject,
         message: String,
+        /* The bytes of `message` when they weren't valid UTF-8 */
+        #[serde(default)]
+        raw_message: Option<Vec<u8>>,
     },
     /* 0x61 */
     Esc(),
@@ -2122,5 +3248,75 @@ pub enum Action
     /* 0x75 */
     TriggerArrow { 
         key: ArrowKeyEvent,
+    },
+
+    /* Reforged (>= 1.32) only */
+
+    /* 0x76 */
+    MouseAction {
+        /* 1 byte */
+        event: u8,
+        /* 4 bytes */
+        x: f32,
+        /* 4 bytes */
+        y: f32,
+        /* 1 byte */
+        button: u8,
+    },
+    /* 0x77 */
+    W3Api {
+        /* 4 bytes */
+        command_id: u32,
+        /* 4 bytes */
+        data: u32,
+        /* u32 for buffer size */
+        buffer: Vec<u8>,
+    },
+    /* 0x78 */
+    BlzSync {
+        identifier: String,
+        value: String,
+        /* 4 bytes */
+        unknown: u32,
+    },
+    /* 0x79 */
+    CommandFrame {
+        /* 4 bytes */
+        unknown_a: u32,
+        /* 4 bytes */
+        unknown_b: u32,
+        /* 4 bytes */
+        event_id: u32,
+        /* 4 bytes */
+        value: f32,
+        text: String,
+    },
+    /* 0x7A */
+    Unknown7A {
+        /* 20 bytes */
+        unknown: Vec<u8>,
+    },
+    /* 0x7B */
+    Unknown7B {
+        /* 16 bytes */
+        unknown: Vec<u8>,
+    },
+    /* 0xA0 */
+    UnknownA0 {
+        /* 14 bytes */
+        unknown: Vec<u8>,
+    },
+    /* 0xA1 */
+    UnknownA1 {
+        /* 9 bytes */
+        unknown: Vec<u8>,
+    },
+
+    /* Any action id that isn't understood, only produced by `ParseMode::Lenient` */
+    Unknown {
+        /* 1 byte */
+        id: u8,
+        /* Everything left in the command after the id */
+        bytes: Vec<u8>,
     }
 }
\ No newline at end of file
@@ -0,0 +1,114 @@
+/*
+    Just enough of the protobuf wire format to read/write the flat messages Reforged embeds in replays.
+
+    https://developers.google.com/protocol-buffers/docs/encoding
+*/
+
+use ::errors::*;
+
+
+const WIRE_TYPE_VARINT: u8 = 0;
+const WIRE_TYPE_64_BIT: u8 = 1;
+const WIRE_TYPE_LENGTH_DELIMITED: u8 = 2;
+const WIRE_TYPE_32_BIT: u8 = 5;
+
+#[derive(Debug, PartialEq)]
+pub enum FieldValue
+{
+    Varint(u64),
+    Bytes(Vec<u8>),
+    /* 64-bit & 32-bit fields, nothing in replays uses them yet */
+    Fixed(Vec<u8>),
+}
+
+/// Splits a message into its `(field_number, value)` pairs in the order they were encoded
+pub fn decode_fields(data: &[u8]) -> Result<Vec<(u32, FieldValue)>>
+{
+    let mut fields = Vec::new();
+    let mut offset = 0;
+
+    while offset < data.len()
+    {
+        let key = read_varint(data, &mut offset)?;
+        let field_number = (key >> 3) as u32;
+
+        let value = match (key & 0b111) as u8
+        {
+            WIRE_TYPE_VARINT => FieldValue::Varint(read_varint(data, &mut offset)?),
+            WIRE_TYPE_LENGTH_DELIMITED =>
+            {
+                let length = read_varint(data, &mut offset)? as usize;
+                FieldValue::Bytes(read_slice(data, &mut offset, length)?)
+            },
+            WIRE_TYPE_64_BIT => FieldValue::Fixed(read_slice(data, &mut offset, 8)?),
+            WIRE_TYPE_32_BIT => FieldValue::Fixed(read_slice(data, &mut offset, 4)?),
+            wire_type => bail!(format!("{} is not a supported protobuf wire type", wire_type)),
+        };
+
+        fields.push((field_number, value));
+    }
+
+    Ok(fields)
+}
+
+pub fn encode_varint_field(out: &mut Vec<u8>, field_number: u32, value: u64)
+{
+    write_varint(out, ((field_number as u64) << 3) | WIRE_TYPE_VARINT as u64);
+    write_varint(out, value);
+}
+
+pub fn encode_bytes_field(out: &mut Vec<u8>, field_number: u32, value: &[u8])
+{
+    write_varint(out, ((field_number as u64) << 3) | WIRE_TYPE_LENGTH_DELIMITED as u64);
+    write_varint(out, value.len() as u64);
+    out.extend_from_slice(value);
+}
+
+fn read_varint(data: &[u8], offset: &mut usize) -> Result<u64>
+{
+    let mut value = 0u64;
+    let mut shift = 0;
+
+    loop
+    {
+        let byte = *data.get(*offset)
+            .ok_or("protobuf varint ran past the end of the message")?;
+        *offset = *offset + 1;
+
+        value = value | (((byte & 0x7F) as u64) << shift);
+        if byte & 0x80 == 0
+        {
+            return Ok(value);
+        }
+
+        shift = shift + 7;
+        if shift >= 64
+        {
+            bail!("protobuf varint is longer than 64 bits");
+        }
+    }
+}
+
+fn read_slice(data: &[u8], offset: &mut usize, length: usize) -> Result<Vec<u8>>
+{
+    let end = *offset + length;
+    if end > data.len()
+    {
+        bail!(format!("protobuf field of {} bytes ran past the end of the message", length));
+    }
+
+    let slice = data[*offset..end].to_vec();
+    *offset = end;
+
+    Ok(slice)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64)
+{
+    while value >= 0x80
+    {
+        out.push(((value & 0x7F) as u8) | 0x80);
+        value = value >> 7;
+    }
+    out.push(value as u8);
+}
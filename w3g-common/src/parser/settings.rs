@@ -0,0 +1,223 @@
+use byteorder::{ReadBytesExt, LittleEndian};
+
+use std::io::{Cursor, BufRead};
+
+use super::parser::{GameHeader, GameSpeed};
+use super::encoding::{self, StringEncoding};
+
+use ::errors::*;
+
+
+/// Size of the SHA-1 of the map that newer patches append after the host name
+const MAP_SHA1_LENGTH: usize = 20;
+
+/// Settings chosen in the lobby, decoded from `GameHeader.encoded_string`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct GameSettings
+{
+    /* byte 0, bits 0-1 */
+    pub speed: GameSpeed,
+    /* byte 1, bits 0-3 */
+    pub visibility: Vec<Visibility>,
+    /* byte 1, bits 4-5 */
+    pub observers: ObserverMode,
+    /* byte 1, bit 6 */
+    pub teams_together: bool,
+    /* byte 2, bits 1-2 */
+    pub fixed_teams: bool,
+    /* byte 3, bit 0 */
+    pub full_shared_unit_control: bool,
+    /* byte 3, bit 1 */
+    pub random_hero: bool,
+    /* byte 3, bit 2 */
+    pub random_races: bool,
+    /* byte 3, bit 6 */
+    pub referees: bool,
+
+    /* 1 byte */
+    pub unknown: u8,
+    /* 1 word, playable area */
+    pub map_width: u16,
+    /* 1 word, playable area */
+    pub map_height: u16,
+    /* 1 dword */
+    pub map_checksum: u32,
+    /* e.g. `Maps\Download\IDProt4.0.1.w3x` */
+    pub map_path: String,
+
+    pub host_name: String,
+
+    /* 20 bytes, only present in newer patches */
+    pub map_sha1: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum Visibility
+{
+    HideTerrain = 0b1,
+    MapExplored = 0b10,
+    AlwaysVisible = 0b100,
+    Default = 0b1000,
+}
+
+impl Visibility
+{
+    fn from_u8(byte: u8) -> Vec<Visibility>
+    {
+        vec![ Visibility::HideTerrain
+            , Visibility::MapExplored
+            , Visibility::AlwaysVisible
+            , Visibility::Default
+            ]
+            .into_iter()
+            .filter(|flag| byte & (*flag as u8) != 0)
+            .collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+#[repr(u8)]
+pub enum ObserverMode
+{
+    Off = 0,
+    Unused = 1,
+    OnDefeat = 2,
+    Full = 3,
+}
+
+impl ObserverMode
+{
+    fn from_u8(byte: u8) -> Result<ObserverMode>
+    {
+        match byte
+        {
+            0 => Ok(ObserverMode::Off),
+            1 => Ok(ObserverMode::Unused),
+            2 => Ok(ObserverMode::OnDefeat),
+            3 => Ok(ObserverMode::Full),
+            _ => bail!(ErrorKind::InvalidValue(String::from("ObserverMode"), byte as u64)),
+        }
+    }
+}
+
+impl GameSettings
+{
+    /// Decodes `GameHeader.encoded_string` (with or without its trailing '\0')
+    pub fn decode(encoded_string: &[u8]) -> Result<GameSettings>
+    {
+        GameSettings::decode_with_encoding(encoded_string, StringEncoding::Strict, None)
+    }
+
+    /// `string_encoding` & `language_id` are for the map path & host name, see `ParseOptions.string_encoding`
+    pub fn decode_with_encoding(encoded_string: &[u8], string_encoding: StringEncoding, language_id: Option<u32>) -> Result<GameSettings>
+    {
+        let decoded = decode_string(encoded_string);
+        let mut cursor = Cursor::new(&decoded[..]);
+
+        let speed = cursor.read_u8()?;
+        let visibility = cursor.read_u8()?;
+        let teams = cursor.read_u8()?;
+        let options = cursor.read_u8()?;
+
+        let unknown = cursor.read_u8()?;
+        let map_width = cursor.read_u16::<LittleEndian>()?;
+        let map_height = cursor.read_u16::<LittleEndian>()?;
+        let map_checksum = cursor.read_u32::<LittleEndian>()?;
+        let map_path = read_null_terminated_string(&mut cursor, string_encoding, language_id)?;
+        let host_name = read_null_terminated_string(&mut cursor, string_encoding, language_id)?;
+
+        /* An empty string and then the sha1 */
+        let remaining = &decoded[cursor.position() as usize..];
+        let map_sha1 = if remaining.len() == MAP_SHA1_LENGTH + 1
+        {
+            Some(remaining[1..].to_vec())
+        } else
+        {
+            None
+        };
+
+        Ok(
+            GameSettings {
+                speed: GameSpeed::from_u8(speed & 0b11)?,
+                visibility: Visibility::from_u8(visibility & 0b1111),
+                observers: ObserverMode::from_u8((visibility >> 4) & 0b11)?,
+                teams_together: visibility & 0b100_0000 != 0,
+                fixed_teams: teams & 0b110 != 0,
+                full_shared_unit_control: options & 0b1 != 0,
+                random_hero: options & 0b10 != 0,
+                random_races: options & 0b100 != 0,
+                referees: options & 0b100_0000 != 0,
+
+                unknown,
+                map_width,
+                map_height,
+                map_checksum,
+                map_path,
+                host_name,
+                map_sha1,
+            }
+        )
+    }
+
+    /// The map's file name without the folders, e.g. `IDProt4.0.1.w3x`
+    pub fn map_file_name(&self) -> &str
+    {
+        self.map_path.rsplit(|c| c == '\\' || c == '/')
+            .next()
+            .unwrap_or(&self.map_path)
+    }
+}
+
+impl GameHeader
+{
+    /// `encoded_string` is always kept so the host name (often not UTF-8) is decoded with the codepage of `language_id`
+    pub fn game_settings(&self) -> Result<GameSettings>
+    {
+        GameSettings::decode_with_encoding(&self.encoded_string, StringEncoding::Guess, Some(self.language_id))
+    }
+}
+
+/// The first byte of every 8 is a mask for the next 7, a cleared bit means 1 was added to that byte so it wouldn't be '\0'
+fn decode_string(encoded_string: &[u8]) -> Vec<u8>
+{
+    let mut decoded = Vec::with_capacity(encoded_string.len());
+    let mut mask = 0u8;
+
+    for (index, byte) in encoded_string.iter().enumerate()
+    {
+        if *byte == 0x0
+        {
+            break;
+        }
+
+        if index % 8 == 0
+        {
+            mask = *byte;
+        } else if mask & (1 << (index % 8)) == 0
+        {
+            decoded.push(byte.wrapping_sub(1));
+        } else
+        {
+            decoded.push(*byte);
+        }
+    }
+
+    decoded
+}
+
+fn read_null_terminated_string(cursor: &mut Cursor<&[u8]>, string_encoding: StringEncoding, language_id: Option<u32>) -> Result<String>
+{
+    let mut buffer = Vec::new();
+    cursor.read_until(0x0, &mut buffer)?;
+
+    if buffer.pop() != Some(0x0)
+    {
+        bail!("String did not end in \\0");
+    }
+
+    match encoding::decode_string(buffer, string_encoding, language_id)
+    {
+        Some((string, _)) => Ok(string),
+        None => bail!("String is not valid UTF-8"),
+    }
+}
@@ -0,0 +1,329 @@
+/*
+    `SlotRecord` keeps the lobby as raw bytes, these decode it.
+    Slots are in the order of the lobby so a slot's index is the player number the map sees (e.g. `Player(0)` is red),
+    which isn't the same as `PlayerRecord.player_id`: that's the order players joined in.
+*/
+
+use super::parser::{Replay, PlayerRecord, SlotRecord};
+
+
+/// 1.29 raised the player limit from 12 to 24, moving observers onto team 24
+pub const MAX_PLAYERS_VERSION_NUMBER: u32 = 29;
+
+/// `SlotRecord.slot_status`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum SlotStatus
+{
+    /* 0x00 */
+    Open,
+    /* 0x01 */
+    Closed,
+    /* 0x02 */
+    Used,
+    Unknown(u8),
+}
+
+impl SlotStatus
+{
+    pub fn from_u8(status: u8) -> SlotStatus
+    {
+        match status
+        {
+            0x00 => SlotStatus::Open,
+            0x01 => SlotStatus::Closed,
+            0x02 => SlotStatus::Used,
+            _ => SlotStatus::Unknown(status),
+        }
+    }
+}
+
+/// `SlotRecord.player_flag`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum SlotController
+{
+    /* 0x00 */
+    Human,
+    /* 0x01 */
+    Computer,
+    Unknown(u8),
+}
+
+impl SlotController
+{
+    pub fn from_u8(flag: u8) -> SlotController
+    {
+        match flag
+        {
+            0x00 => SlotController::Human,
+            0x01 => SlotController::Computer,
+            _ => SlotController::Unknown(flag),
+        }
+    }
+}
+
+/// Bits of `SlotRecord.race`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum Race
+{
+    Human = 0x01,
+    Orc = 0x02,
+    NightElf = 0x04,
+    Undead = 0x08,
+    Random = 0x20,
+    /* The race can be changed in the lobby, i.e. the map doesn't fix it */
+    Selectable = 0x40,
+}
+
+impl Race
+{
+    /// Every flag that's set, unknown bits are ignored
+    pub fn from_u8(byte: u8) -> Vec<Race>
+    {
+        vec![ Race::Human
+            , Race::Orc
+            , Race::NightElf
+            , Race::Undead
+            , Race::Random
+            , Race::Selectable
+            ]
+            .into_iter()
+            .filter(|&race| byte & race as u8 != 0)
+            .collect()
+    }
+}
+
+/// `SlotRecord.color`, the last 12 were added in 1.29
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum PlayerColor
+{
+    Red,
+    Blue,
+    Teal,
+    Purple,
+    Yellow,
+    Orange,
+    Green,
+    Pink,
+    Gray,
+    LightBlue,
+    DarkGreen,
+    Brown,
+    Maroon,
+    Navy,
+    Turquoise,
+    Violet,
+    Wheat,
+    Peach,
+    Mint,
+    Lavender,
+    Coal,
+    Snow,
+    Emerald,
+    Peanut,
+    Unknown(u8),
+}
+
+impl PlayerColor
+{
+    pub fn from_u8(color: u8) -> PlayerColor
+    {
+        match color
+        {
+            0 => PlayerColor::Red,
+            1 => PlayerColor::Blue,
+            2 => PlayerColor::Teal,
+            3 => PlayerColor::Purple,
+            4 => PlayerColor::Yellow,
+            5 => PlayerColor::Orange,
+            6 => PlayerColor::Green,
+            7 => PlayerColor::Pink,
+            8 => PlayerColor::Gray,
+            9 => PlayerColor::LightBlue,
+            10 => PlayerColor::DarkGreen,
+            11 => PlayerColor::Brown,
+            12 => PlayerColor::Maroon,
+            13 => PlayerColor::Navy,
+            14 => PlayerColor::Turquoise,
+            15 => PlayerColor::Violet,
+            16 => PlayerColor::Wheat,
+            17 => PlayerColor::Peach,
+            18 => PlayerColor::Mint,
+            19 => PlayerColor::Lavender,
+            20 => PlayerColor::Coal,
+            21 => PlayerColor::Snow,
+            22 => PlayerColor::Emerald,
+            23 => PlayerColor::Peanut,
+            _ => PlayerColor::Unknown(color),
+        }
+    }
+}
+
+/// `SlotRecord.ai_strength`, only meaningful for computers
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum AiStrength
+{
+    /* 0x00 */
+    Easy,
+    /* 0x01 */
+    Normal,
+    /* 0x02 */
+    Insane,
+    Unknown(u8),
+}
+
+impl AiStrength
+{
+    pub fn from_u8(strength: u8) -> AiStrength
+    {
+        match strength
+        {
+            0x00 => AiStrength::Easy,
+            0x01 => AiStrength::Normal,
+            0x02 => AiStrength::Insane,
+            _ => AiStrength::Unknown(strength),
+        }
+    }
+}
+
+/// `SlotRecord.team_number`
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum SlotTeam
+{
+    /* 0-indexed like the lobby */
+    Team(u8),
+    Observer,
+    /* Observers are referees when `GameSettings.referees` is set */
+    Referee,
+}
+
+impl SlotTeam
+{
+    pub fn from_u8(team_number: u8, version_number: u32, referees: bool) -> SlotTeam
+    {
+        let observer_team = if version_number >= MAX_PLAYERS_VERSION_NUMBER { 24 } else { 12 };
+
+        match team_number
+        {
+            team_number if team_number != observer_team => SlotTeam::Team(team_number),
+            _ if referees => SlotTeam::Referee,
+            _ => SlotTeam::Observer,
+        }
+    }
+
+    pub fn is_observer(&self) -> bool
+    {
+        *self == SlotTeam::Observer || *self == SlotTeam::Referee
+    }
+}
+
+impl SlotRecord
+{
+    pub fn status(&self) -> SlotStatus
+    {
+        SlotStatus::from_u8(self.slot_status)
+    }
+
+    pub fn controller(&self) -> SlotController
+    {
+        SlotController::from_u8(self.player_flag)
+    }
+
+    pub fn races(&self) -> Vec<Race>
+    {
+        Race::from_u8(self.race)
+    }
+
+    pub fn color(&self) -> PlayerColor
+    {
+        PlayerColor::from_u8(self.color)
+    }
+
+    /// `None` for anyone other than a computer
+    pub fn ai_strength(&self) -> Option<AiStrength>
+    {
+        match self.controller()
+        {
+            SlotController::Computer => Some(AiStrength::from_u8(self.ai_strength)),
+            _ => None,
+        }
+    }
+
+    /// The observer team moved in 1.29, see `ReplayHeader.version_number`
+    pub fn team(&self, version_number: u32, referees: bool) -> SlotTeam
+    {
+        SlotTeam::from_u8(self.team_number, version_number, referees)
+    }
+}
+
+/// A used slot along with the player in it
+#[derive(Debug, Serialize, PartialEq, Clone)]
+pub struct LobbyPlayer<'a>
+{
+    /* Position in `GameRecord.slot_records`, the map's player number */
+    pub slot_index: u8,
+    pub slot: &'a SlotRecord,
+    /* `None` for computers, which don't get a `PlayerRecord` */
+    pub player: Option<&'a PlayerRecord>,
+    pub is_replay_saver: bool,
+    pub controller: SlotController,
+    pub team: SlotTeam,
+    /* `None` for observers & referees, they keep whatever color the slot had */
+    pub color: Option<PlayerColor>,
+    pub races: Vec<Race>,
+    pub ai_strength: Option<AiStrength>,
+    pub handicap: u8,
+}
+
+impl<'a> LobbyPlayer<'a>
+{
+    pub fn player_id(&self) -> Option<u8>
+    {
+        self.player.map(|player| player.player_id)
+    }
+
+    pub fn player_name(&self) -> Option<&'a str>
+    {
+        self.player.map(|player| player.player_name.as_str())
+    }
+}
+
+impl Replay
+{
+    /// Every used slot in lobby order joined with its `PlayerRecord` (including the replay saver's)
+    pub fn players<'a>(&'a self) -> Vec<LobbyPlayer<'a>>
+    {
+        let version_number = self.replay_header.version_number;
+        /* Can't tell observers from referees without the settings so they're assumed to be observers */
+        let referees = self.game_header.game_settings()
+            .map(|settings| settings.referees)
+            .unwrap_or(false);
+
+        self.game_header.game_record.slot_records.iter()
+            .enumerate()
+            .filter(|&(_, slot)| slot.status() == SlotStatus::Used)
+            .map(|(slot_index, slot)|
+            {
+                let controller = slot.controller();
+                let team = slot.team(version_number, referees);
+                let player = match controller
+                {
+                    SlotController::Computer => None,
+                    _ => self.game_header.player(slot.player_id),
+                };
+
+                LobbyPlayer {
+                    slot_index: slot_index as u8,
+                    slot,
+                    player,
+                    is_replay_saver: player.map(|player| player.player_id == self.game_header.replay_saver.player_id).unwrap_or(false),
+                    controller,
+                    team,
+                    color: if team.is_observer() { None } else { Some(slot.color()) },
+                    races: slot.races(),
+                    ai_strength: slot.ai_strength(),
+                    handicap: slot.handicap,
+                }
+            })
+            .collect()
+    }
+}
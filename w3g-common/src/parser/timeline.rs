@@ -0,0 +1,76 @@
+use super::parser::{Replay, ReplayBlock, Command, Action};
+
+
+/// Something that happened during the game along with when it happened
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TimelineEntry<'a>
+{
+    /* Milliseconds of game time since the game started, time spent paused isn't counted */
+    pub time: u32,
+    /* Whether the game was paused when this happened */
+    pub paused: bool,
+    pub event: TimelineEvent<'a>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TimelineEvent<'a>
+{
+    /* Every block, including the ticks that the commands below came from */
+    Block(&'a ReplayBlock),
+    Command(&'a Command),
+    Action {
+        player_id: u8,
+        action: &'a Action,
+    },
+}
+
+impl Replay
+{
+    /// Flattens `replay_blocks` into one entry per block, command & action stamped with the game time it happened at.
+    ///
+    /// Commands are given the time at the end of their tick which is when the game runs them.
+    /// Ticks that arrive between a `PauseGame` and a `ResumeGame` don't advance the game time.
+    pub fn timeline<'a>(&'a self) -> Vec<TimelineEntry<'a>>
+    {
+        let mut entries = Vec::new();
+        let mut time = 0u32;
+        let mut paused = false;
+
+        for block in self.replay_blocks.iter()
+        {
+            let commands = match *block
+            {
+                ReplayBlock::Tick { time_increment, ref commands, .. } | ReplayBlock::TickPreOverflow { time_increment, ref commands, .. } =>
+                {
+                    if !paused
+                    {
+                        time = time + time_increment as u32;
+                    }
+                    Some(commands)
+                },
+                _ => None,
+            };
+
+            entries.push(TimelineEntry { time, paused, event: TimelineEvent::Block(block) });
+
+            for command in commands.into_iter().flat_map(|commands| commands.iter())
+            {
+                entries.push(TimelineEntry { time, paused, event: TimelineEvent::Command(command) });
+
+                for action in command.actions.iter()
+                {
+                    entries.push(TimelineEntry { time, paused, event: TimelineEvent::Action { player_id: command.player_id, action } });
+
+                    match *action
+                    {
+                        Action::PauseGame {} => paused = true,
+                        Action::ResumeGame {} => paused = false,
+                        _ => {},
+                    }
+                }
+            }
+        }
+
+        entries
+    }
+}
@@ -0,0 +1,440 @@
+/*
+    Zero-copy alternative to `parse_replay`.
+
+    Every block is decompressed into one contiguous buffer up front and the blocks are read straight out of it,
+    chat messages and commands borrow from that buffer instead of being copied into their own allocations.
+    Actions are only decoded when `CommandView::actions` is called, their strings & bytes borrow from the same buffer.
+*/
+
+use std::borrow::Cow;
+
+use super::parser::{ReplayReader, ReplayStream, ParseOptions, ParseWarning, ReplayHeader, GameHeader, ReplayBlock, Action};
+use super::parser::{GameObject, UnitInventory, UnitAbility};
+use super::error::ParseContext;
+
+use ::errors::*;
+
+
+/// The headers of a replay and all of its decompressed data
+#[derive(Debug, PartialEq)]
+pub struct ReplayData
+{
+    pub magic_string: String,
+    pub file_offset: u32,
+    pub compressed_size: u32,
+    pub header_version: u32,
+    pub decompressed_size: u32,
+    pub number_of_compressed_blocks: u32,
+
+    pub replay_header: ReplayHeader,
+    /* Parsed the same way as `parse_replay`, it's only read once so there's little to gain from borrowing */
+    pub game_header: GameHeader,
+
+    /* Only checksum problems, the blocks have nothing to warn about and each command's actions come with their own from `CommandView::actions` */
+    pub warnings: Vec<ParseWarning>,
+
+    options: ParseOptions,
+    /* Offset of `data` within the decompressed stream, i.e. the size of the `GameHeader` */
+    data_offset: u64,
+    /* Everything after the `GameHeader` */
+    data: Vec<u8>,
+}
+
+/// `ReplayBlock` with the variants that own data replaced by ones that borrow from `ReplayData`
+#[derive(Debug, PartialEq)]
+pub enum ReplayBlockView<'a>
+{
+    /* 0x1E */
+    TickPreOverflow {
+        num_bytes: u16,
+        time_increment: u16,
+        commands: Vec<CommandView<'a>>,
+    },
+    /* 0x1F */
+    Tick {
+        num_bytes: u16,
+        time_increment: u16,
+        commands: Vec<CommandView<'a>>,
+    },
+    /* 0x20 */
+    PlayerChat {
+        player_id: u8,
+        num_bytes: u16,
+        flags: u8,
+        chat_mode: u32,
+        /* Only copied when it wasn't valid UTF-8, see `ParseOptions.string_encoding` */
+        message: Cow<'a, str>,
+        raw_message: &'a [u8],
+    },
+    /* Every other block, none of them have anything to borrow */
+    Other(ReplayBlock),
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CommandView<'a>
+{
+    pub player_id: u8,
+    pub num_bytes: u16,
+    /* The encoded actions, `num_bytes` long */
+    pub data: &'a [u8],
+
+    pub(crate) options: ParseOptions,
+    pub(crate) version_number: u32,
+    pub(crate) language_id: Option<u32>,
+    /* Where `data` starts */
+    pub(crate) context: ParseContext,
+}
+
+impl<'a> CommandView<'a>
+{
+    /// Decodes `data` the same way `parse_replay` would have, without copying it
+    /// 
+    /// # Return
+    /// * The actions and what was tolerated while decoding them, only ever populated when parsing with `ParseMode::Lenient`
+    pub fn actions(&self) -> Result<(Vec<ActionView<'a>>, Vec<ParseWarning>)>
+    {
+        let mut stream = ReplayStream::from_slice(self.data, self.options, self.version_number, self.language_id, self.context.clone());
+        let actions = stream.extract_action_views(self.player_id, self.data.len());
+        let actions = stream.add_context(actions)?;
+
+        Ok((actions, stream.into_warnings()))
+    }
+}
+
+/// `Action` with the variants that own strings or bytes replaced by ones that borrow from `ReplayData`
+#[derive(Debug, PartialEq)]
+pub enum ActionView<'a>
+{
+    /* 0x06 */
+    SaveGame {
+        game_name: Cow<'a, str>,
+    },
+    /* 0x60 */
+    MapTriggerChat {
+        event: GameObject,
+        /* Only copied when it wasn't valid UTF-8, see `ParseOptions.string_encoding` */
+        message: Cow<'a, str>,
+        raw_message: &'a [u8],
+    },
+    /* 0x6B */
+    SyncStoredInteger {
+        file: Cow<'a, str>,
+        group: Cow<'a, str>,
+        key: Cow<'a, str>,
+        value: i32,
+    },
+    /* 0x6C */
+    SyncStoredFloat {
+        file: Cow<'a, str>,
+        group: Cow<'a, str>,
+        key: Cow<'a, str>,
+        value: f32,
+    },
+    /* 0x6D */
+    SyncStoredBoolean {
+        file: Cow<'a, str>,
+        group: Cow<'a, str>,
+        key: Cow<'a, str>,
+        value: u32,
+    },
+    /* 0x6E */
+    SyncStoredUnit {
+        file: Cow<'a, str>,
+        group: Cow<'a, str>,
+        key: Cow<'a, str>,
+        unit_type: u32,
+        inventory: Vec<UnitInventory>,
+        experience: u32,
+        level_ups: u32,
+        skill_points: u32,
+        proper_name_index: u16,
+        unknown1: u16,
+        base_strength: u32,
+        bonus_strength_per_level: f32,
+        base_agility: u32,
+        bonus_move_speed: f32,
+        bonus_attack_speed: f32,
+        bonus_agility_per_level: f32,
+        base_intelligence: u32,
+        bonus_intelligence_per_level: f32,
+        abilities: Vec<UnitAbility>,
+        bonus_health: f32,
+        bonus_mana: f32,
+        sight_radius_day: f32,
+        unknown2: u32,
+        unknown3: u32,
+        unknown4: u32,
+        unknown5: u32,
+        hotkey_flags: u16,
+    },
+    /* 0x6F */
+    SyncStoredString {
+        file: Cow<'a, str>,
+        group: Cow<'a, str>,
+        key: Cow<'a, str>,
+        value: Cow<'a, str>,
+    },
+    /* 0x70 */
+    SyncEmptyInteger {
+        file: Cow<'a, str>,
+        group: Cow<'a, str>,
+        key: Cow<'a, str>,
+    },
+    /* 0x71 */
+    SyncEmptyString {
+        file: Cow<'a, str>,
+        group: Cow<'a, str>,
+        key: Cow<'a, str>,
+    },
+    /* 0x72 */
+    SyncEmptyBoolean {
+        file: Cow<'a, str>,
+        group: Cow<'a, str>,
+        key: Cow<'a, str>,
+    },
+    /* 0x73 */
+    SyncEmptyUnit {
+        file: Cow<'a, str>,
+        group: Cow<'a, str>,
+        key: Cow<'a, str>,
+    },
+    /* 0x74 */
+    SyncEmptyFloat {
+        file: Cow<'a, str>,
+        group: Cow<'a, str>,
+        key: Cow<'a, str>,
+    },
+    /* 0x77 */
+    W3Api {
+        command_id: u32,
+        data: u32,
+        buffer: &'a [u8],
+    },
+    /* 0x78 */
+    BlzSync {
+        identifier: Cow<'a, str>,
+        value: Cow<'a, str>,
+        unknown: u32,
+    },
+    /* 0x79 */
+    CommandFrame {
+        unknown_a: u32,
+        unknown_b: u32,
+        event_id: u32,
+        value: f32,
+        text: Cow<'a, str>,
+    },
+    /* 0x7A */
+    Unknown7A {
+        unknown: &'a [u8],
+    },
+    /* 0x7B */
+    Unknown7B {
+        unknown: &'a [u8],
+    },
+    /* 0xA0 */
+    UnknownA0 {
+        unknown: &'a [u8],
+    },
+    /* 0xA1 */
+    UnknownA1 {
+        unknown: &'a [u8],
+    },
+    /* Every other action, none of them have anything to borrow apart from `Action::Unknown` which only `ParseMode::Lenient` produces */
+    Other(Action),
+}
+
+impl<'a> ActionView<'a>
+{
+    /// Copies whatever is borrowed, giving the `Action` `parse_replay` would have
+    pub fn into_action(self) -> Action
+    {
+        match self
+        {
+            ActionView::SaveGame { game_name } => Action::SaveGame { game_name: game_name.into_owned() },
+            ActionView::MapTriggerChat { event, message, raw_message } =>
+            {
+                /* `parse_replay` only keeps the bytes when they had to be decoded */
+                let raw_message = match message
+                {
+                    Cow::Borrowed(_) => None,
+                    Cow::Owned(_) => Some(raw_message.to_vec()),
+                };
+
+                Action::MapTriggerChat { event, message: message.into_owned(), raw_message }
+            },
+            ActionView::SyncStoredInteger { file, group, key, value } =>
+                Action::SyncStoredInteger { file: file.into_owned(), group: group.into_owned(), key: key.into_owned(), value },
+            ActionView::SyncStoredFloat { file, group, key, value } =>
+                Action::SyncStoredFloat { file: file.into_owned(), group: group.into_owned(), key: key.into_owned(), value },
+            ActionView::SyncStoredBoolean { file, group, key, value } =>
+                Action::SyncStoredBoolean { file: file.into_owned(), group: group.into_owned(), key: key.into_owned(), value },
+            ActionView::SyncStoredUnit {
+                file,
+                group,
+                key,
+                unit_type,
+                inventory,
+                experience,
+                level_ups,
+                skill_points,
+                proper_name_index,
+                unknown1,
+                base_strength,
+                bonus_strength_per_level,
+                base_agility,
+                bonus_move_speed,
+                bonus_attack_speed,
+                bonus_agility_per_level,
+                base_intelligence,
+                bonus_intelligence_per_level,
+                abilities,
+                bonus_health,
+                bonus_mana,
+                sight_radius_day,
+                unknown2,
+                unknown3,
+                unknown4,
+                unknown5,
+                hotkey_flags,
+            } => Action::SyncStoredUnit {
+                file: file.into_owned(),
+                group: group.into_owned(),
+                key: key.into_owned(),
+                unit_type,
+                inventory,
+                experience,
+                level_ups,
+                skill_points,
+                proper_name_index,
+                unknown1,
+                base_strength,
+                bonus_strength_per_level,
+                base_agility,
+                bonus_move_speed,
+                bonus_attack_speed,
+                bonus_agility_per_level,
+                base_intelligence,
+                bonus_intelligence_per_level,
+                abilities,
+                bonus_health,
+                bonus_mana,
+                sight_radius_day,
+                unknown2,
+                unknown3,
+                unknown4,
+                unknown5,
+                hotkey_flags,
+            },
+            ActionView::SyncStoredString { file, group, key, value } =>
+                Action::SyncStoredString { file: file.into_owned(), group: group.into_owned(), key: key.into_owned(), value: value.into_owned() },
+            ActionView::SyncEmptyInteger { file, group, key } =>
+                Action::SyncEmptyInteger { file: file.into_owned(), group: group.into_owned(), key: key.into_owned() },
+            ActionView::SyncEmptyString { file, group, key } =>
+                Action::SyncEmptyString { file: file.into_owned(), group: group.into_owned(), key: key.into_owned() },
+            ActionView::SyncEmptyBoolean { file, group, key } =>
+                Action::SyncEmptyBoolean { file: file.into_owned(), group: group.into_owned(), key: key.into_owned() },
+            ActionView::SyncEmptyUnit { file, group, key } =>
+                Action::SyncEmptyUnit { file: file.into_owned(), group: group.into_owned(), key: key.into_owned() },
+            ActionView::SyncEmptyFloat { file, group, key } =>
+                Action::SyncEmptyFloat { file: file.into_owned(), group: group.into_owned(), key: key.into_owned() },
+            ActionView::W3Api { command_id, data, buffer } => Action::W3Api { command_id, data, buffer: buffer.to_vec() },
+            ActionView::BlzSync { identifier, value, unknown } =>
+                Action::BlzSync { identifier: identifier.into_owned(), value: value.into_owned(), unknown },
+            ActionView::CommandFrame { unknown_a, unknown_b, event_id, value, text } =>
+                Action::CommandFrame { unknown_a, unknown_b, event_id, value, text: text.into_owned() },
+            ActionView::Unknown7A { unknown } => Action::Unknown7A { unknown: unknown.to_vec() },
+            ActionView::Unknown7B { unknown } => Action::Unknown7B { unknown: unknown.to_vec() },
+            ActionView::UnknownA0 { unknown } => Action::UnknownA0 { unknown: unknown.to_vec() },
+            ActionView::UnknownA1 { unknown } => Action::UnknownA1 { unknown: unknown.to_vec() },
+            ActionView::Other(action) => action,
+        }
+    }
+}
+
+pub fn parse_replay_bytes(raw: &[u8]) -> Result<ReplayData>
+{
+    parse_replay_bytes_with_options(raw, ParseOptions::default())
+}
+
+/// Parses the headers and decompresses every block, the blocks themselves are read by `ReplayData::blocks`
+pub fn parse_replay_bytes_with_options(raw: &[u8], options: ParseOptions) -> Result<ReplayData>
+{
+    let mut reader = ReplayReader::with_options(raw, options)?;
+    let data = reader.decompress_remaining()?;
+    let data_offset = reader.offset();
+    let warnings = reader.warnings().clone();
+
+    Ok(
+        ReplayData
+        {
+            magic_string: reader.magic_string,
+            file_offset: reader.file_offset,
+            compressed_size: reader.compressed_size,
+            header_version: reader.header_version,
+            decompressed_size: reader.decompressed_size,
+            number_of_compressed_blocks: reader.number_of_compressed_blocks,
+
+            replay_header: reader.replay_header,
+            game_header: reader.game_header,
+
+            warnings,
+
+            options,
+            data_offset,
+            data,
+        }
+    )
+}
+
+impl ReplayData
+{
+    /// Iterates over the blocks, stopping after the terminating block or the first error
+    pub fn blocks<'a>(&'a self) -> ReplayBlockViews<'a>
+    {
+        let context = ParseContext { offset: self.data_offset, ..ParseContext::default() };
+
+        ReplayBlockViews
+        {
+            stream: ReplayStream::from_slice(&self.data, self.options, self.replay_header.version_number, Some(self.game_header.language_id), context),
+            finished: false,
+        }
+    }
+}
+
+pub struct ReplayBlockViews<'a>
+{
+    /* Over `ReplayData.data` */
+    stream: ReplayStream<&'a [u8]>,
+    finished: bool,
+}
+
+impl<'a> Iterator for ReplayBlockViews<'a>
+{
+    type Item = Result<ReplayBlockView<'a>>;
+
+    fn next(&mut self) -> Option<Result<ReplayBlockView<'a>>>
+    {
+        /* Trailing padding after the terminating block is optional */
+        if self.finished || self.stream.is_empty()
+        {
+            return None;
+        }
+
+        let block = self.stream.extract_block_view();
+        match self.stream.add_context(block)
+        {
+            Ok(Some(block)) => Some(Ok(block)),
+            Ok(None) =>
+            {
+                self.finished = true;
+                None
+            },
+            Err(error) =>
+            {
+                self.finished = true;
+                Some(Err(error))
+            },
+        }
+    }
+}
@@ -0,0 +1,452 @@
+/*
+    W3MMD, the protocol maps use to report results & stats to bots.
+
+    Every message is the key of a `SyncStoredInteger` in the "MMD.Dat" file, the group is "val:<id>" where id counts up from 0.
+    Each message is followed by a checksum in group "chk:<id>", it's kept on the message but not verified.
+    Arguments are separated by spaces, spaces & backslashes within an argument are escaped with a backslash.
+
+        init version <minimum> <current>
+        init pid <pid> <name>
+        DefVarP <name> <int|real|string> <high|low|none> <none|track|leaderboard>
+        VarP <pid> <name> <=|+=|-=> <value>
+        FlagP <pid> <winner|loser|drawer|leaver|practicing>
+        DefEvent <name> <argument count> <argument names>... <format>
+        Event <name> <arguments>...
+        Blank
+        Custom <anything>
+*/
+
+use std::collections::BTreeMap;
+
+use super::parser::Replay;
+use super::game_cache::{GameCacheChange, GameCacheValue};
+
+use ::errors::*;
+
+
+pub const W3MMD_FILE: &'static str = "MMD.Dat";
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum W3mmdFlag
+{
+    Winner,
+    Loser,
+    Drawer,
+    Leaver,
+    Practicing,
+}
+
+impl W3mmdFlag
+{
+    pub fn from_str(flag: &str) -> Result<W3mmdFlag>
+    {
+        Ok(
+            match flag
+            {
+                "winner" => W3mmdFlag::Winner,
+                "loser" => W3mmdFlag::Loser,
+                "drawer" => W3mmdFlag::Drawer,
+                "leaver" => W3mmdFlag::Leaver,
+                "practicing" => W3mmdFlag::Practicing,
+                _ => bail!("{} is not a W3MMD flag", flag),
+            }
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum W3mmdType
+{
+    Int,
+    Real,
+    String,
+}
+
+/// Whether a variable is better when it's higher or lower
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum W3mmdGoal
+{
+    High,
+    Low,
+    None,
+}
+
+/// How the map suggests a variable is shown
+#[derive(Debug, Serialize, Deserialize, PartialEq, Copy, Clone)]
+pub enum W3mmdSuggestion
+{
+    None,
+    Track,
+    Leaderboard,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub enum W3mmdValue
+{
+    Int(i32),
+    Real(f64),
+    String(String),
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct W3mmdVariable
+{
+    pub name: String,
+    pub value_type: W3mmdType,
+    pub goal: W3mmdGoal,
+    pub suggestion: W3mmdSuggestion,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct W3mmdEventDefinition
+{
+    pub name: String,
+    pub argument_names: Vec<String>,
+    /* e.g. "{0} killed {1}" where {N} is replaced by the Nth argument */
+    pub format: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct W3mmdEvent
+{
+    /* Milliseconds of game time, see `Replay::timeline` */
+    pub time: u32,
+    pub name: String,
+    pub arguments: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct W3mmdPlayer
+{
+    /* The map's player number, 0-indexed unlike `PlayerRecord.player_id` */
+    pub pid: u32,
+    pub name: String,
+    /* The `PlayerRecord` with the same name, if there is one */
+    pub player_id: Option<u8>,
+    /* In the order they were first set, without duplicates */
+    pub flags: Vec<W3mmdFlag>,
+    pub variables: BTreeMap<String, W3mmdValue>,
+}
+
+impl W3mmdPlayer
+{
+    /// The last of winner, loser or drawer that the map flagged the player with
+    pub fn result(&self) -> Option<W3mmdFlag>
+    {
+        self.flags.iter()
+            .rev()
+            .find(|flag| match **flag
+            {
+                W3mmdFlag::Winner | W3mmdFlag::Loser | W3mmdFlag::Drawer => true,
+                _ => false,
+            })
+            .cloned()
+    }
+}
+
+/// A single `SyncStoredInteger` in the "val" group
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct W3mmdMessage
+{
+    pub time: u32,
+    /* Who sent the `SyncStoredInteger`, not who the message is about */
+    pub player_id: u8,
+    pub id: u32,
+    pub message: String,
+    pub value: i32,
+    /* From the matching "chk" group, `None` when the map never sent one */
+    pub checksum: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct W3mmdWarning
+{
+    pub message: W3mmdMessage,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct W3mmd
+{
+    /* (minimum, current) from `init version` */
+    pub version: Option<(u32, u32)>,
+    pub players: Vec<W3mmdPlayer>,
+    pub variables: Vec<W3mmdVariable>,
+    pub event_definitions: Vec<W3mmdEventDefinition>,
+    pub events: Vec<W3mmdEvent>,
+    /* Every message in the order they were sent */
+    pub messages: Vec<W3mmdMessage>,
+    /* Messages that couldn't be understood, they're otherwise ignored */
+    pub warnings: Vec<W3mmdWarning>,
+}
+
+impl W3mmd
+{
+    pub fn player(&self, pid: u32) -> Option<&W3mmdPlayer>
+    {
+        self.players.iter().find(|player| player.pid == pid)
+    }
+
+    fn apply(&mut self, replay: &Replay, message: &W3mmdMessage) -> Result<()>
+    {
+        let arguments = split_arguments(&message.message)?;
+        let arguments = arguments.iter().map(|argument| argument.as_str()).collect::<Vec<&str>>();
+
+        match arguments.as_slice()
+        {
+            ["init", "version", minimum, current] =>
+            {
+                self.version = Some((minimum.parse()?, current.parse()?));
+            },
+            ["init", "pid", pid, name] =>
+            {
+                let pid = pid.parse()?;
+                let player_id = Some(&replay.game_header.replay_saver).into_iter()
+                    .chain(replay.game_header.players.iter())
+                    .find(|player| player.player_name == *name)
+                    .map(|player| player.player_id);
+
+                self.players.retain(|player| player.pid != pid);
+                self.players.push( W3mmdPlayer {
+                    pid,
+                    name: String::from(*name),
+                    player_id,
+                    flags: Vec::new(),
+                    variables: BTreeMap::new(),
+                });
+            },
+            ["DefVarP", name, value_type, goal, suggestion] =>
+            {
+                let value_type = match *value_type
+                {
+                    "int" => W3mmdType::Int,
+                    "real" => W3mmdType::Real,
+                    "string" => W3mmdType::String,
+                    _ => bail!("{} is not a W3MMD type", value_type),
+                };
+                let goal = match *goal
+                {
+                    "high" => W3mmdGoal::High,
+                    "low" => W3mmdGoal::Low,
+                    "none" => W3mmdGoal::None,
+                    _ => bail!("{} is not a W3MMD goal", goal),
+                };
+                let suggestion = match *suggestion
+                {
+                    "none" => W3mmdSuggestion::None,
+                    "track" => W3mmdSuggestion::Track,
+                    "leaderboard" => W3mmdSuggestion::Leaderboard,
+                    _ => bail!("{} is not a W3MMD suggestion", suggestion),
+                };
+
+                self.variables.retain(|variable| variable.name != *name);
+                self.variables.push(W3mmdVariable { name: String::from(*name), value_type, goal, suggestion });
+            },
+            ["VarP", pid, name, operation, value] =>
+            {
+                let pid: u32 = pid.parse()?;
+                let value_type = match self.variables.iter().find(|variable| variable.name == *name)
+                {
+                    Some(variable) => variable.value_type,
+                    None => bail!("{} was never defined", name),
+                };
+                let player = match self.players.iter_mut().find(|player| player.pid == pid)
+                {
+                    Some(player) => player,
+                    None => bail!("pid {} was never initialised", pid),
+                };
+
+                let current = player.variables.get(*name).cloned();
+                let value = match (value_type, *operation, current)
+                {
+                    (W3mmdType::Int, "=", _) => W3mmdValue::Int(value.parse()?),
+                    (W3mmdType::Int, "+=", current) => W3mmdValue::Int(int_or_zero(current).wrapping_add(value.parse()?)),
+                    (W3mmdType::Int, "-=", current) => W3mmdValue::Int(int_or_zero(current).wrapping_sub(value.parse()?)),
+                    (W3mmdType::Real, "=", _) => W3mmdValue::Real(parse_real(value)?),
+                    (W3mmdType::Real, "+=", current) => W3mmdValue::Real(real_or_zero(current) + parse_real(value)?),
+                    (W3mmdType::Real, "-=", current) => W3mmdValue::Real(real_or_zero(current) - parse_real(value)?),
+                    (W3mmdType::String, "=", _) => W3mmdValue::String(String::from(value.trim_matches('"'))),
+                    _ => bail!("{} can't be used on {}", operation, name),
+                };
+
+                player.variables.insert(String::from(*name), value);
+            },
+            ["FlagP", pid, flag] =>
+            {
+                let pid: u32 = pid.parse()?;
+                let flag = W3mmdFlag::from_str(flag)?;
+                let player = match self.players.iter_mut().find(|player| player.pid == pid)
+                {
+                    Some(player) => player,
+                    None => bail!("pid {} was never initialised", pid),
+                };
+
+                if !player.flags.contains(&flag)
+                {
+                    player.flags.push(flag);
+                }
+            },
+            /* Subslice patterns aren't stable on the toolchain the services are built with, hence the guards */
+            _ if arguments[0] == "DefEvent" && arguments.len() >= 3 =>
+            {
+                let name = arguments[1];
+                let argument_count: usize = arguments[2].parse()?;
+                let rest = &arguments[3..];
+                if rest.len() != argument_count + 1
+                {
+                    bail!("{} has {} arguments instead of {} and a format", name, rest.len(), argument_count);
+                }
+
+                self.event_definitions.retain(|definition| definition.name != name);
+                self.event_definitions.push( W3mmdEventDefinition {
+                    name: String::from(name),
+                    argument_names: rest[..argument_count].iter().map(|argument| String::from(*argument)).collect(),
+                    format: String::from(rest[argument_count]),
+                });
+            },
+            _ if arguments[0] == "Event" && arguments.len() >= 2 =>
+            {
+                let name = arguments[1];
+                let rest = &arguments[2..];
+                match self.event_definitions.iter().find(|definition| definition.name == name)
+                {
+                    Some(definition) if definition.argument_names.len() != rest.len() =>
+                        bail!("{} takes {} arguments not {}", name, definition.argument_names.len(), rest.len()),
+                    Some(_) => {},
+                    None => bail!("{} was never defined", name),
+                }
+
+                self.events.push( W3mmdEvent {
+                    time: message.time,
+                    name: String::from(name),
+                    arguments: rest.iter().map(|argument| String::from(*argument)).collect(),
+                });
+            },
+            ["Blank"] => {},
+            _ if arguments[0] == "Custom" => {},
+            _ => bail!("unknown message"),
+        }
+
+        Ok(())
+    }
+}
+
+fn int_or_zero(value: Option<W3mmdValue>) -> i32
+{
+    match value
+    {
+        Some(W3mmdValue::Int(value)) => value,
+        _ => 0,
+    }
+}
+
+fn real_or_zero(value: Option<W3mmdValue>) -> f64
+{
+    match value
+    {
+        Some(W3mmdValue::Real(value)) => value,
+        _ => 0.0,
+    }
+}
+
+fn parse_real(value: &str) -> Result<f64>
+{
+    Ok(value.parse().map_err(|_| format!("{} is not a real", value))?)
+}
+
+/// Splits on spaces that aren't escaped, removing the escapes
+fn split_arguments(message: &str) -> Result<Vec<String>>
+{
+    let mut arguments = Vec::new();
+    let mut argument = String::new();
+
+    let mut chars = message.chars();
+    while let Some(c) = chars.next()
+    {
+        match c
+        {
+            '\\' => match chars.next()
+            {
+                Some(escaped) => argument.push(escaped),
+                None => bail!("ends with an escape"),
+            },
+            ' ' =>
+            {
+                arguments.push(argument);
+                argument = String::new();
+            },
+            _ => argument.push(c),
+        }
+    }
+    arguments.push(argument);
+
+    Ok(arguments)
+}
+
+/// "val:3" to 3
+fn group_id<'a>(group: &'a str, prefix: &str) -> Option<u32>
+{
+    if group.starts_with(prefix)
+    {
+        group[prefix.len()..].parse().ok()
+    } else
+    {
+        None
+    }
+}
+
+impl Replay
+{
+    /// Decodes the W3MMD messages the map sent, empty when the map doesn't use W3MMD
+    pub fn w3mmd(&self) -> W3mmd
+    {
+        let entries = self.game_cache().into_iter()
+            .filter(|entry| entry.file == W3MMD_FILE)
+            .filter_map(|entry| match entry.change
+            {
+                GameCacheChange::Store(GameCacheValue::Integer(value)) => Some((entry, value)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let checksums = entries.iter()
+            .filter_map(|&(ref entry, value)| group_id(entry.group, "chk:").map(|id| (id, value)))
+            .collect::<BTreeMap<u32, i32>>();
+
+        let mut w3mmd = W3mmd {
+            version: None,
+            players: Vec::new(),
+            variables: Vec::new(),
+            event_definitions: Vec::new(),
+            events: Vec::new(),
+            messages: Vec::new(),
+            warnings: Vec::new(),
+        };
+
+        for &(ref entry, value) in entries.iter()
+        {
+            let id = match group_id(entry.group, "val:")
+            {
+                Some(id) => id,
+                None => continue,
+            };
+
+            let message = W3mmdMessage {
+                time: entry.time,
+                player_id: entry.player_id,
+                id,
+                message: String::from(entry.key),
+                value,
+                checksum: checksums.get(&id).cloned(),
+            };
+
+            if let Err(error) = w3mmd.apply(self, &message)
+            {
+                w3mmd.warnings.push(W3mmdWarning { message: message.clone(), reason: error.to_string() });
+            }
+            w3mmd.messages.push(message);
+        }
+
+        w3mmd
+    }
+}
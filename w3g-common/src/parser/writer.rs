@@ -0,0 +1,782 @@
+use byteorder::{WriteBytesExt, LittleEndian};
+
+use std::fs::File;
+
+use std::io::Write;
+
+use libflate::zlib::Encoder;
+
+use crc::crc32;
+
+use super::parser::*;
+use super::encoding;
+
+use ::errors::*;
+
+
+/// Size of the header that precedes the compressed data for `header_version` 1
+const HEADER_SIZE: u32 = 0x44;
+
+/// Size of the header that precedes the compressed data for `header_version` 0
+const LEGACY_HEADER_SIZE: u32 = 0x40;
+
+/// Every compressed block inflates to exactly this many bytes, the last block is padded with 0s
+const DECOMPRESSED_BLOCK_SIZE: usize = 8192;
+
+/// Serializes `replay` into the .w3g format
+///
+/// Sizes (`num_bytes`, `compressed_size`, ...) and checksums are recomputed from the content rather than trusted so hand built replays are written correctly.
+pub fn write_replay(replay: &Replay, out: &mut Write) -> Result<()>
+{
+    let header_size = match replay.header_version
+    {
+        0 => LEGACY_HEADER_SIZE,
+        1 => HEADER_SIZE,
+        _ => bail!(format!("Unable to write header_version: {}", replay.header_version)),
+    };
+    let version_number = replay.replay_header.version_number;
+
+    let mut data = Vec::new();
+    write_game_header(&mut data, &replay.game_header, version_number)?;
+    for block in replay.replay_blocks.iter()
+    {
+        write_block(&mut data, block, version_number)?;
+    }
+    let decompressed_size = data.len() as u32;
+
+    /* The parser stops at the first 0x0 block id so there needs to be at least 1 byte of padding */
+    let padding = DECOMPRESSED_BLOCK_SIZE - (data.len() % DECOMPRESSED_BLOCK_SIZE);
+    data.extend(vec![0u8; padding]);
+
+    let reforged = replay.replay_header.is_reforged();
+    let mut compressed_blocks = Vec::new();
+    for chunk in data.chunks(DECOMPRESSED_BLOCK_SIZE)
+    {
+        compressed_blocks.extend(compress_block(chunk, reforged)?);
+    }
+    let number_of_compressed_blocks = (data.len() / DECOMPRESSED_BLOCK_SIZE) as u32;
+
+    let mut header = Vec::with_capacity(header_size as usize);
+    write_fixed_length_string(&mut header, &replay.magic_string, 28)?;
+    header.write_u32::<LittleEndian>(header_size)?;
+    header.write_u32::<LittleEndian>(header_size + compressed_blocks.len() as u32)?;
+    header.write_u32::<LittleEndian>(replay.header_version)?;
+    header.write_u32::<LittleEndian>(decompressed_size)?;
+    header.write_u32::<LittleEndian>(number_of_compressed_blocks)?;
+
+    let replay_header = &replay.replay_header;
+    if replay.header_version == 0
+    {
+        /* unknown, always 0 */
+        header.write_u16::<LittleEndian>(0)?;
+        header.write_u16::<LittleEndian>(replay_header.version_number as u16)?;
+    } else 
+    {
+        write_fixed_length_string(&mut header, &replay_header.version_string, 4)?;
+        header.write_u32::<LittleEndian>(replay_header.version_number)?;
+    }
+    header.write_u16::<LittleEndian>(replay_header.build_number)?;
+    header.write_u16::<LittleEndian>(replay_header.flags)?;
+    header.write_u32::<LittleEndian>(replay_header.duration)?;
+
+    /* The crc is computed over the header with the crc itself zeroed */
+    let crc32 = crc32::checksum_ieee(&[&header[..], &[0u8; 4]].concat());
+    header.write_u32::<LittleEndian>(crc32)?;
+
+    out.write_all(&header)?;
+    out.write_all(&compressed_blocks)?;
+
+    Ok(())
+}
+
+pub fn save_replay(replay: &Replay, path: &str) -> Result<()>
+{
+    let mut file = File::create(path)?;
+
+    write_replay(replay, &mut file)
+}
+
+fn compress_block(chunk: &[u8], reforged: bool) -> Result<Vec<u8>>
+{
+    let mut encoder = Encoder::new(Vec::new())?;
+    encoder.write_all(chunk)?;
+    let compressed_data = encoder.finish().into_result()?;
+
+    let mut block = Vec::with_capacity(12 + compressed_data.len());
+    /* Reforged widened the sizes to dwords */
+    if reforged
+    {
+        block.write_u32::<LittleEndian>(compressed_data.len() as u32)?;
+        block.write_u32::<LittleEndian>(chunk.len() as u32)?;
+    } else 
+    {
+        block.write_u16::<LittleEndian>(compressed_data.len() as u16)?;
+        block.write_u16::<LittleEndian>(chunk.len() as u16)?;
+    }
+
+    /*
+        Low word is the crc of the block header (with the crc zeroed) and the high word is the crc of the compressed data.
+        Each crc32 is folded in half by xor'ing its words together.
+    */
+    let header_crc = crc32::checksum_ieee(&[&block[..], &[0u8; 4]].concat());
+    let data_crc = crc32::checksum_ieee(&compressed_data);
+    let crc = ((header_crc ^ (header_crc >> 16)) & 0xFFFF) | (((data_crc ^ (data_crc >> 16)) & 0xFFFF) << 16);
+    block.write_u32::<LittleEndian>(crc)?;
+
+    block.extend(compressed_data);
+
+    Ok(block)
+}
+
+fn write_fixed_length_string(out: &mut Vec<u8>, value: &str, length: usize) -> Result<()>
+{
+    if value.len() != length
+    {
+        bail!(format!("{:?} is not {} bytes long", value, length));
+    }
+
+    out.write_all(value.as_bytes())?;
+
+    Ok(())
+}
+
+fn write_null_terminated_string(out: &mut Vec<u8>, value: &str) -> Result<()>
+{
+    out.write_all(value.as_bytes())?;
+    out.write_u8(0x0)?;
+
+    Ok(())
+}
+
+/// The original bytes of strings that weren't valid UTF-8, otherwise (or once the string has been edited) the string itself
+fn string_bytes<'a>(value: &'a str, raw: &'a Option<Vec<u8>>) -> &'a [u8]
+{
+    match *raw
+    {
+        Some(ref raw) if encoding::decodes_to(raw, value) => raw,
+        _ => value.as_bytes(),
+    }
+}
+
+fn write_raw_string(out: &mut Vec<u8>, value: &str, raw: &Option<Vec<u8>>) -> Result<()>
+{
+    out.write_all(string_bytes(value, raw))?;
+    out.write_u8(0x0)?;
+
+    Ok(())
+}
+
+fn write_game_object(out: &mut Vec<u8>, object: &GameObject) -> Result<()>
+{
+    out.write_u32::<LittleEndian>(object.allocated_id)?;
+    out.write_u32::<LittleEndian>(object.counter_id)?;
+
+    Ok(())
+}
+
+fn write_game_header(out: &mut Vec<u8>, header: &GameHeader, version_number: u32) -> Result<()>
+{
+    out.write_u32::<LittleEndian>(header.unknown)?;
+    write_player_record(out, &header.replay_saver)?;
+    write_raw_string(out, &header.game_name, &header.raw_game_name)?;
+    out.write_u8(0x0)?;
+    /* Unlike the other strings the encoded string was kept with its '\0' */
+    out.write_all(&header.encoded_string)?;
+    out.write_u32::<LittleEndian>(header.number_of_players)?;
+    out.write_u32::<LittleEndian>(header.game_type)?;
+    out.write_u32::<LittleEndian>(header.language_id)?;
+
+    for player in header.players.iter()
+    {
+        write_player_record(out, player)?;
+        out.write_u32::<LittleEndian>(player.unknown.unwrap_or(0))?;
+    }
+
+    for metadata in header.reforged_metadata.iter()
+    {
+        let (subtype, data) = match metadata
+        {
+            ReforgedMetadata::Player(player) => (0x03, player.to_protobuf()),
+            ReforgedMetadata::Unknown { subtype, data } => (*subtype, data.clone()),
+        };
+
+        out.write_u8(0x39)?;
+        out.write_u8(subtype)?;
+        out.write_u32::<LittleEndian>(data.len() as u32)?;
+        out.write_all(&data)?;
+    }
+
+    write_game_record(out, &header.game_record, version_number)
+}
+
+fn write_player_record(out: &mut Vec<u8>, record: &PlayerRecord) -> Result<()>
+{
+    out.write_u8(record.record_id)?;
+    out.write_u8(record.player_id)?;
+    write_raw_string(out, &record.player_name, &record.raw_player_name)?;
+    out.write_u8(record.additional_data.len() as u8)?;
+    out.write_all(&record.additional_data)?;
+
+    Ok(())
+}
+
+fn write_game_record(out: &mut Vec<u8>, record: &GameRecord, version_number: u32) -> Result<()>
+{
+    let mut slots = Vec::new();
+    for slot in record.slot_records.iter()
+    {
+        slots.write_u8(slot.player_id)?;
+        slots.write_u8(slot.download_percent)?;
+        slots.write_u8(slot.slot_status)?;
+        slots.write_u8(slot.player_flag)?;
+        slots.write_u8(slot.team_number)?;
+        slots.write_u8(slot.color)?;
+        slots.write_u8(slot.race)?;
+        if version_number >= SLOT_AI_STRENGTH_VERSION_NUMBER
+        {
+            slots.write_u8(slot.ai_strength)?;
+        }
+        if version_number >= SLOT_HANDICAP_VERSION_NUMBER
+        {
+            slots.write_u8(slot.handicap)?;
+        }
+    }
+
+    out.write_u8(record.record_id)?;
+    // 1 for num_slot_records, 4 for random_seed, 1 for select_mode, 1 for start_spot_count
+    out.write_u16::<LittleEndian>((slots.len() + 7) as u16)?;
+    out.write_u8(record.slot_records.len() as u8)?;
+    out.write_all(&slots)?;
+    out.write_u32::<LittleEndian>(record.random_seed)?;
+    out.write_u8(record.select_mode)?;
+    out.write_u8(record.start_spot_count)?;
+
+    Ok(())
+}
+
+fn write_block(out: &mut Vec<u8>, block: &ReplayBlock, version_number: u32) -> Result<()>
+{
+    match block
+    {
+        ReplayBlock::LeaveGame { reason, player_id, result, session_leave_count } =>
+        {
+            out.write_u8(0x17)?;
+            out.write_u32::<LittleEndian>(*reason)?;
+            out.write_u8(*player_id)?;
+            out.write_u32::<LittleEndian>(*result)?;
+            out.write_u32::<LittleEndian>(*session_leave_count)?;
+        },
+        ReplayBlock::LoadStarted1 { unknown } =>
+        {
+            out.write_u8(0x1A)?;
+            out.write_u32::<LittleEndian>(*unknown)?;
+        },
+        ReplayBlock::LoadStarted2 { unknown } =>
+        {
+            out.write_u8(0x1B)?;
+            out.write_u32::<LittleEndian>(*unknown)?;
+        },
+        ReplayBlock::GameStarted { unknown } =>
+        {
+            out.write_u8(0x1C)?;
+            out.write_u32::<LittleEndian>(*unknown)?;
+        },
+        ReplayBlock::TickPreOverflow { num_bytes: _, time_increment, commands } =>
+        {
+            out.write_u8(0x1E)?;
+            write_tick(out, *time_increment, commands, version_number)?;
+        },
+        ReplayBlock::Tick { num_bytes: _, time_increment, commands } =>
+        {
+            out.write_u8(0x1F)?;
+            write_tick(out, *time_increment, commands, version_number)?;
+        },
+        ReplayBlock::PlayerChat { player_id, num_bytes: _, flags, chat_mode, message, raw_message } =>
+        {
+            let message_size = string_bytes(message, raw_message).len();
+            out.write_u8(0x20)?;
+            out.write_u8(*player_id)?;
+            if *flags == LOBBY_CHAT_FLAGS
+            {
+                // 1 for flags, 1 for '\0'
+                out.write_u16::<LittleEndian>((message_size + 2) as u16)?;
+                out.write_u8(*flags)?;
+            } else
+            {
+                // 1 for flags, 4 for chat_mode, 1 for '\0'
+                out.write_u16::<LittleEndian>((message_size + 6) as u16)?;
+                out.write_u8(*flags)?;
+                out.write_u32::<LittleEndian>(*chat_mode)?;
+            }
+            write_raw_string(out, message, raw_message)?;
+        },
+        ReplayBlock::RandomSeed { num_bytes: _, unknown } =>
+        {
+            out.write_u8(0x22)?;
+            out.write_u8(4)?;
+            out.write_u32::<LittleEndian>(*unknown)?;
+        },
+        ReplayBlock::Desync { tick_count, checksum, remaining_players } =>
+        {
+            out.write_u8(0x23)?;
+            out.write_u32::<LittleEndian>(*tick_count)?;
+            out.write_u32::<LittleEndian>(*checksum)?;
+            out.write_u8(*remaining_players)?;
+        },
+        ReplayBlock::ForceGameEndCountdown { mode, time } =>
+        {
+            out.write_u8(0x2F)?;
+            out.write_u32::<LittleEndian>(*mode)?;
+            out.write_u32::<LittleEndian>(*time)?;
+        },
+    }
+
+    Ok(())
+}
+
+fn write_tick(out: &mut Vec<u8>, time_increment: u16, commands: &Vec<Command>, version_number: u32) -> Result<()>
+{
+    let mut data = Vec::new();
+    for command in commands.iter()
+    {
+        let mut actions = Vec::new();
+        for action in command.actions.iter()
+        {
+            write_action(&mut actions, action, version_number)?;
+        }
+
+        data.write_u8(command.player_id)?;
+        data.write_u16::<LittleEndian>(actions.len() as u16)?;
+        data.write_all(&actions)?;
+    }
+
+    // plus 2 because 2 for time_increment
+    out.write_u16::<LittleEndian>((data.len() + 2) as u16)?;
+    out.write_u16::<LittleEndian>(time_increment)?;
+    out.write_all(&data)?;
+
+    Ok(())
+}
+
+fn order_flags_to_u16(flags: &Vec<OrderType>) -> u16
+{
+    flags.iter().fold(0, |acc, flag| acc | (*flag as u16))
+}
+
+fn alliance_flags_to_u32(flags: &Vec<AllianceType>) -> u32
+{
+    flags.iter().fold(0, |acc, flag| acc | (*flag as u32))
+}
+
+fn write_order(out: &mut Vec<u8>, flags: &Vec<OrderType>, order_id: u32, unknown: &GameObject, version_number: u32) -> Result<()>
+{
+    if version_number < ORDER_FLAGS_WORD_VERSION_NUMBER
+    {
+        out.write_u8(order_flags_to_u16(flags) as u8)?;
+    } else 
+    {
+        out.write_u16::<LittleEndian>(order_flags_to_u16(flags))?;
+    }
+    out.write_u32::<LittleEndian>(order_id)?;
+
+    if version_number < ORDER_OBJECT_VERSION_NUMBER
+    {
+        return Ok(());
+    }
+    write_game_object(out, unknown)
+}
+
+fn write_cache_key(out: &mut Vec<u8>, file: &str, group: &str, key: &str) -> Result<()>
+{
+    write_null_terminated_string(out, file)?;
+    write_null_terminated_string(out, group)?;
+    write_null_terminated_string(out, key)
+}
+
+fn write_fixed_length_action(out: &mut Vec<u8>, action_id: u8, unknown: &Vec<u8>, length: usize) -> Result<()>
+{
+    if unknown.len() != length
+    {
+        bail!(format!("Action {} must be {} bytes but was {}", action_id, length, unknown.len()));
+    }
+
+    out.write_u8(action_id)?;
+    out.write_all(unknown)?;
+
+    Ok(())
+}
+
+fn write_action(out: &mut Vec<u8>, action: &Action, version_number: u32) -> Result<()>
+{
+    match action
+    {
+        Action::PauseGame() => out.write_u8(0x01)?,
+        Action::ResumeGame() => out.write_u8(0x02)?,
+        Action::SetGameSpeed { speed } =>
+        {
+            out.write_u8(0x03)?;
+            out.write_u8(*speed as u8)?;
+        },
+        Action::IncreaseGameSpeed() => out.write_u8(0x04)?,
+        Action::DecreaseGameSpeed() => out.write_u8(0x05)?,
+        Action::SaveGame { game_name } =>
+        {
+            out.write_u8(0x06)?;
+            write_null_terminated_string(out, game_name)?;
+        },
+        Action::SaveGameFinish { unknown } =>
+        {
+            out.write_u8(0x07)?;
+            out.write_u32::<LittleEndian>(*unknown)?;
+        },
+        Action::SelfOrder { flags, order_id, unknown } =>
+        {
+            out.write_u8(0x10)?;
+            write_order(out, flags, *order_id, unknown, version_number)?;
+        },
+        Action::PointOrder { flags, order_id, unknown, x, y } =>
+        {
+            out.write_u8(0x11)?;
+            write_order(out, flags, *order_id, unknown, version_number)?;
+            out.write_f32::<LittleEndian>(*x)?;
+            out.write_f32::<LittleEndian>(*y)?;
+        },
+        Action::ObjectOrder { flags, order_id, unknown, x, y, target } =>
+        {
+            out.write_u8(0x12)?;
+            write_order(out, flags, *order_id, unknown, version_number)?;
+            out.write_f32::<LittleEndian>(*x)?;
+            out.write_f32::<LittleEndian>(*y)?;
+            write_game_object(out, target)?;
+        },
+        Action::DropOrGiveItem { flags, order_id, unknown, x, y, receiver, item } =>
+        {
+            out.write_u8(0x13)?;
+            write_order(out, flags, *order_id, unknown, version_number)?;
+            out.write_f32::<LittleEndian>(*x)?;
+            out.write_f32::<LittleEndian>(*y)?;
+            write_game_object(out, receiver)?;
+            write_game_object(out, item)?;
+        },
+        Action::FogObjectOrder { flags, order_id, unknown, x, y, target_type, target_flags, target_owner, target_x, target_y } =>
+        {
+            out.write_u8(0x14)?;
+            write_order(out, flags, *order_id, unknown, version_number)?;
+            out.write_f32::<LittleEndian>(*x)?;
+            out.write_f32::<LittleEndian>(*y)?;
+            out.write_u32::<LittleEndian>(*target_type)?;
+            out.write_u64::<LittleEndian>(*target_flags)?;
+            out.write_u8(*target_owner)?;
+            out.write_f32::<LittleEndian>(*target_x)?;
+            out.write_f32::<LittleEndian>(*target_y)?;
+        },
+        Action::ChangeSelection { select_mode, targets } =>
+        {
+            out.write_u8(0x16)?;
+            out.write_u8(*select_mode as u8)?;
+            out.write_u16::<LittleEndian>(targets.len() as u16)?;
+            for target in targets.iter()
+            {
+                write_game_object(out, target)?;
+            }
+        },
+        Action::AssignGroup { group_number, targets } =>
+        {
+            out.write_u8(0x17)?;
+            out.write_u8(*group_number)?;
+            out.write_u16::<LittleEndian>(targets.len() as u16)?;
+            for target in targets.iter()
+            {
+                write_game_object(out, target)?;
+            }
+        },
+        Action::SelectGroup { group_number, unknown } =>
+        {
+            out.write_u8(0x18)?;
+            out.write_u8(*group_number)?;
+            out.write_u8(*unknown)?;
+        },
+        Action::SelectSubGroup { item_id, target } =>
+        {
+            out.write_u8(0x19)?;
+            out.write_u32::<LittleEndian>(*item_id)?;
+            write_game_object(out, target)?;
+        },
+        Action::SelectSubGroupIndex { subgroup } =>
+        {
+            out.write_u8(0x19)?;
+            out.write_u8(*subgroup)?;
+        },
+        Action::PreSubSelection() => out.write_u8(0x1A)?,
+        Action::TriggerSelectionEvent { operation, target } =>
+        {
+            out.write_u8(0x1B)?;
+            out.write_u8(*operation as u8)?;
+            write_game_object(out, target)?;
+        },
+        Action::SelectGroundItem { flags, target } =>
+        {
+            out.write_u8(0x1C)?;
+            out.write_u8(*flags)?;
+            write_game_object(out, target)?;
+        },
+        Action::CancelHeroRevival { target } =>
+        {
+            out.write_u8(0x1D)?;
+            write_game_object(out, target)?;
+        },
+        Action::CancelUnitInQueue { slot_index, unit_id } =>
+        {
+            out.write_u8(0x1E)?;
+            out.write_u8(*slot_index)?;
+            out.write_u32::<LittleEndian>(*unit_id)?;
+        },
+        Action::Unknown21 { unknown_a, unknown_b } =>
+        {
+            out.write_u8(0x21)?;
+            out.write_u32::<LittleEndian>(*unknown_a)?;
+            out.write_u32::<LittleEndian>(*unknown_b)?;
+        },
+        Action::CheatTheDudeAbides() => out.write_u8(0x20)?,
+        Action::CheatSomebodySetUpUsTheBomb() => out.write_u8(0x22)?,
+        Action::CheatWarpTen() => out.write_u8(0x23)?,
+        Action::CheatIocainePowder() => out.write_u8(0x24)?,
+        Action::CheatPointBreak() => out.write_u8(0x25)?,
+        Action::CheatWhosYourDaddy() => out.write_u8(0x26)?,
+        Action::CheatKeyserSoze { unknown, gold } =>
+        {
+            out.write_u8(0x27)?;
+            out.write_u8(*unknown)?;
+            out.write_i32::<LittleEndian>(*gold)?;
+        },
+        Action::CheatLeafItToMe { unknown, lumber } =>
+        {
+            out.write_u8(0x28)?;
+            out.write_u8(*unknown)?;
+            out.write_i32::<LittleEndian>(*lumber)?;
+        },
+        Action::CheatThereIsNoSpoon() => out.write_u8(0x29)?,
+        Action::CheatStrengthAndHonor() => out.write_u8(0x2A)?,
+        Action::CheatItVexesMe() => out.write_u8(0x2B)?,
+        Action::CheatWhoIsJohnGalt() => out.write_u8(0x2C)?,
+        Action::CheatGreedIsGood { unknown, resources } =>
+        {
+            out.write_u8(0x2D)?;
+            out.write_u8(*unknown)?;
+            out.write_i32::<LittleEndian>(*resources)?;
+        },
+        Action::CheatDaylightSavings { time } =>
+        {
+            out.write_u8(0x2E)?;
+            out.write_f32::<LittleEndian>(*time)?;
+        },
+        Action::CheatISeeDeadPeople() => out.write_u8(0x2F)?,
+        Action::CheatSynergy() => out.write_u8(0x30)?,
+        Action::CheatSharpAndShiny() => out.write_u8(0x31)?,
+        Action::CheatAllYourBaseAreBelongToUs() => out.write_u8(0x32)?,
+        Action::ChangeAlly { player_id, flags } =>
+        {
+            out.write_u8(0x50)?;
+            out.write_u8(*player_id)?;
+            out.write_u32::<LittleEndian>(alliance_flags_to_u32(flags))?;
+        },
+        Action::TransferResources { player_id, gold_transfered, lumber_transfered } =>
+        {
+            out.write_u8(0x51)?;
+            out.write_u8(*player_id)?;
+            out.write_i32::<LittleEndian>(*gold_transfered)?;
+            out.write_i32::<LittleEndian>(*lumber_transfered)?;
+        },
+        Action::MapTriggerChat { event, message, raw_message } =>
+        {
+            out.write_u8(0x60)?;
+            write_game_object(out, event)?;
+            write_raw_string(out, message, raw_message)?;
+        },
+        Action::Esc() => out.write_u8(0x61)?,
+        Action::TriggerSleepOrSyncFinished { thread, wait_count } =>
+        {
+            out.write_u8(0x62)?;
+            write_game_object(out, thread)?;
+            out.write_u32::<LittleEndian>(*wait_count)?;
+        },
+        Action::TriggerSyncReady { thread } =>
+        {
+            out.write_u8(0x63)?;
+            write_game_object(out, thread)?;
+        },
+        Action::TriggerMouseClickedTrackable { trackable } =>
+        {
+            out.write_u8(0x64)?;
+            write_game_object(out, trackable)?;
+        },
+        Action::TriggerMouseTouchedTrackable { trackable } =>
+        {
+            out.write_u8(0x65)?;
+            write_game_object(out, trackable)?;
+        },
+        Action::EnterHeroSkillSubMenu() => out.write_u8(0x66)?,
+        Action::EnterBuildingSubMenu() => out.write_u8(0x67)?,
+        Action::MiniMapSignal { location_x, location_y, duration } =>
+        {
+            out.write_u8(0x68)?;
+            out.write_f32::<LittleEndian>(*location_x)?;
+            out.write_f32::<LittleEndian>(*location_y)?;
+            out.write_f32::<LittleEndian>(*duration)?;
+        },
+        Action::DialogButtonClicked { dialog, button } =>
+        {
+            out.write_u8(0x69)?;
+            write_game_object(out, dialog)?;
+            write_game_object(out, button)?;
+        },
+        Action::DialogAnyButtonClicked { button, dialog } =>
+        {
+            out.write_u8(0x6A)?;
+            write_game_object(out, button)?;
+            write_game_object(out, dialog)?;
+        },
+        Action::SyncStoredInteger { file, group, key, value } =>
+        {
+            out.write_u8(0x6B)?;
+            write_cache_key(out, file, group, key)?;
+            out.write_i32::<LittleEndian>(*value)?;
+        },
+        Action::SyncStoredFloat { file, group, key, value } =>
+        {
+            out.write_u8(0x6C)?;
+            write_cache_key(out, file, group, key)?;
+            out.write_f32::<LittleEndian>(*value)?;
+        },
+        Action::SyncStoredBoolean { file, group, key, value } =>
+        {
+            out.write_u8(0x6D)?;
+            write_cache_key(out, file, group, key)?;
+            out.write_u32::<LittleEndian>(*value)?;
+        },
+        Action::SyncStoredUnit {
+            file, group, key, unit_type, inventory, experience, level_ups, skill_points, proper_name_index, unknown1,
+            base_strength, bonus_strength_per_level, base_agility, bonus_move_speed, bonus_attack_speed, bonus_agility_per_level,
+            base_intelligence, bonus_intelligence_per_level, abilities, bonus_health, bonus_mana, sight_radius_day,
+            unknown2, unknown3, unknown4, unknown5, hotkey_flags,
+        } =>
+        {
+            out.write_u8(0x6E)?;
+            write_cache_key(out, file, group, key)?;
+            out.write_u32::<LittleEndian>(*unit_type)?;
+            out.write_u32::<LittleEndian>(inventory.len() as u32)?;
+            for slot in inventory.iter()
+            {
+                out.write_u32::<LittleEndian>(slot.item)?;
+                out.write_u32::<LittleEndian>(slot.charges)?;
+                out.write_u32::<LittleEndian>(slot.unknown)?;
+            }
+            out.write_u32::<LittleEndian>(*experience)?;
+            out.write_u32::<LittleEndian>(*level_ups)?;
+            out.write_u32::<LittleEndian>(*skill_points)?;
+            out.write_u16::<LittleEndian>(*proper_name_index)?;
+            out.write_u16::<LittleEndian>(*unknown1)?;
+            out.write_u32::<LittleEndian>(*base_strength)?;
+            out.write_f32::<LittleEndian>(*bonus_strength_per_level)?;
+            out.write_u32::<LittleEndian>(*base_agility)?;
+            out.write_f32::<LittleEndian>(*bonus_move_speed)?;
+            out.write_f32::<LittleEndian>(*bonus_attack_speed)?;
+            out.write_f32::<LittleEndian>(*bonus_agility_per_level)?;
+            out.write_u32::<LittleEndian>(*base_intelligence)?;
+            out.write_f32::<LittleEndian>(*bonus_intelligence_per_level)?;
+            out.write_u32::<LittleEndian>(abilities.len() as u32)?;
+            for ability in abilities.iter()
+            {
+                out.write_u32::<LittleEndian>(ability.ability)?;
+                out.write_u32::<LittleEndian>(ability.level)?;
+            }
+            out.write_f32::<LittleEndian>(*bonus_health)?;
+            out.write_f32::<LittleEndian>(*bonus_mana)?;
+            out.write_f32::<LittleEndian>(*sight_radius_day)?;
+            out.write_u32::<LittleEndian>(*unknown2)?;
+            out.write_u32::<LittleEndian>(*unknown3)?;
+            out.write_u32::<LittleEndian>(*unknown4)?;
+            out.write_u32::<LittleEndian>(*unknown5)?;
+            out.write_u16::<LittleEndian>(*hotkey_flags)?;
+        },
+        Action::SyncStoredString { file, group, key, value } =>
+        {
+            out.write_u8(0x6F)?;
+            write_cache_key(out, file, group, key)?;
+            write_null_terminated_string(out, value)?;
+        },
+        Action::SyncEmptyInteger { file, group, key } =>
+        {
+            out.write_u8(0x70)?;
+            write_cache_key(out, file, group, key)?;
+        },
+        Action::SyncEmptyString { file, group, key } =>
+        {
+            out.write_u8(0x71)?;
+            write_cache_key(out, file, group, key)?;
+        },
+        Action::SyncEmptyBoolean { file, group, key } =>
+        {
+            out.write_u8(0x72)?;
+            write_cache_key(out, file, group, key)?;
+        },
+        Action::SyncEmptyUnit { file, group, key } =>
+        {
+            out.write_u8(0x73)?;
+            write_cache_key(out, file, group, key)?;
+        },
+        Action::SyncEmptyFloat { file, group, key } =>
+        {
+            out.write_u8(0x74)?;
+            write_cache_key(out, file, group, key)?;
+        },
+        Action::TriggerArrow { key } =>
+        {
+            out.write_u8(0x75)?;
+            out.write_u8(*key as u8)?;
+        },
+        Action::MouseAction { event, x, y, button } =>
+        {
+            out.write_u8(0x76)?;
+            out.write_u8(*event)?;
+            out.write_f32::<LittleEndian>(*x)?;
+            out.write_f32::<LittleEndian>(*y)?;
+            out.write_u8(*button)?;
+        },
+        Action::W3Api { command_id, data, buffer } =>
+        {
+            out.write_u8(0x77)?;
+            out.write_u32::<LittleEndian>(*command_id)?;
+            out.write_u32::<LittleEndian>(*data)?;
+            out.write_u32::<LittleEndian>(buffer.len() as u32)?;
+            out.write_all(buffer)?;
+        },
+        Action::BlzSync { identifier, value, unknown } =>
+        {
+            out.write_u8(0x78)?;
+            write_null_terminated_string(out, identifier)?;
+            write_null_terminated_string(out, value)?;
+            out.write_u32::<LittleEndian>(*unknown)?;
+        },
+        Action::CommandFrame { unknown_a, unknown_b, event_id, value, text } =>
+        {
+            out.write_u8(0x79)?;
+            out.write_u32::<LittleEndian>(*unknown_a)?;
+            out.write_u32::<LittleEndian>(*unknown_b)?;
+            out.write_u32::<LittleEndian>(*event_id)?;
+            out.write_f32::<LittleEndian>(*value)?;
+            write_null_terminated_string(out, text)?;
+        },
+        Action::Unknown7A { unknown } => write_fixed_length_action(out, 0x7A, unknown, 20)?,
+        Action::Unknown7B { unknown } => write_fixed_length_action(out, 0x7B, unknown, 16)?,
+        Action::UnknownA0 { unknown } => write_fixed_length_action(out, 0xA0, unknown, 14)?,
+        Action::UnknownA1 { unknown } => write_fixed_length_action(out, 0xA1, unknown, 9)?,
+        Action::Unknown { id, bytes } =>
+        {
+            out.write_u8(*id)?;
+            out.write_all(bytes)?;
+        },
+    }
+
+    Ok(())
+}
@@ -0,0 +1,200 @@
+/*
+    Island Defense keeps track of its players in the "ID.D" game cache file, keyed by the player number (the slot index, see `Replay::players`):
+        class       what the player is playing as, only meaningful until "game_start" is stored
+        flag        1 when the player won and 0 when they lost
+*/
+
+use std::collections::{HashMap, BTreeMap};
+
+use parser::{Replay, ReplayBlock, Action, GameSettings, TimelineEvent};
+use pubsub::model::{Player, IdGameResult, IdTeam};
+use super::{ResultExtractor, GameResult, Team, PlayerRole, Outcome, Confidence};
+
+use ::errors::*;
+
+
+pub const ISLAND_DEFENSE_FILE: &'static str = "ID.D";
+
+pub const BUILDER_TEAM: &'static str = "Builder";
+pub const TITAN_TEAM: &'static str = "Titan";
+
+/* Titans that take longer than this to win are given a tie instead */
+const TITAN_TIME_LIMIT: u32 = 60 * 60 * 1000;
+
+/*
+    public static constant integer CLASS_NONE = 0;
+    public static constant integer CLASS_MINION = 1;
+    public static constant integer CLASS_TITAN = 2;
+    public static constant integer CLASS_DEFENDER = 3;
+    public static constant integer CLASS_OBSERVER = 4;
+*/
+const CLASS_TITAN: i32 = 2;
+const CLASS_NAMES: [&'static str; 5] = ["None", "Minion", "Titan", "Defender", "Observer"];
+
+pub struct IslandDefense;
+
+impl IslandDefense
+{
+    /// Every class other than titan plays (or is counted) as a builder
+    fn team(class: i32) -> &'static str
+    {
+        if class == CLASS_TITAN
+        {
+            TITAN_TEAM
+        } else
+        {
+            BUILDER_TEAM
+        }
+    }
+}
+
+impl ResultExtractor for IslandDefense
+{
+    /// e.g. `IDProt4.0.1.w3x`
+    fn handles_map(&self, settings: &GameSettings) -> bool
+    {
+        let file_name = settings.map_file_name().to_lowercase();
+
+        file_name.starts_with("idprot") || file_name.contains("island defense") || file_name.contains("islanddefense")
+    }
+
+    fn extract(&self, player_list: &Vec<Player>, replay: &Replay) -> Result<GameResult>
+    {
+        /* The website lists players in the order they joined, which is the order of their `PlayerRecord.player_id`s */
+        let mut player_ids = Some(&replay.game_header.replay_saver).into_iter()
+            .chain(replay.game_header.players.iter())
+            .map(|player| player.player_id)
+            .collect::<Vec<u8>>();
+        player_ids.sort();
+        if player_ids.len() != player_list.len()
+        {
+            bail!("The replay has {} players but {} were listed", player_ids.len(), player_list.len());
+        }
+
+        /* ID.D is keyed by slot, computers aren't on the website's list */
+        let players: HashMap<u8, &Player> = replay.players().iter()
+            .filter_map(|lobby_player| lobby_player.player_id().map(|player_id| (lobby_player.slot_index, player_id)))
+            .filter_map(|(slot_index, player_id)| player_ids.iter()
+                .position(|&id| id == player_id)
+                .map(|index| (slot_index, &player_list[index])))
+            .collect();
+
+        let mut classes: BTreeMap<u8, i32> = BTreeMap::new();
+        let mut winner: Option<&'static str> = None;
+        let mut game_started = false;
+
+        for entry in replay.timeline()
+        {
+            let (file, group, key, value) = match entry.event
+            {
+                TimelineEvent::Block(ReplayBlock::Desync { tick_count, checksum, remaining_players }) =>
+                {
+                    warn!("desync occured: count: {:?}, checksum?: {:?}, remaing: {:?}", tick_count, checksum, remaining_players);
+                    continue;
+                },
+                TimelineEvent::Action { action: Action::SyncStoredInteger { file, group, key, value }, .. } => (file, group, key, *value),
+                _ => continue,
+            };
+
+            trace!("f: {:?}, g: {:?}, k: {:?}, v: {:?}", file, group, key, value);
+
+            match (file.as_str(), group.as_str(), key.parse::<u8>())
+            {
+                (ISLAND_DEFENSE_FILE, "flag", Ok(player_index)) =>
+                {
+                    match (players.get(&player_index), classes.get(&player_index), value)
+                    {
+                        (Some(player), Some(class), 0) =>
+                        {
+                            debug!("Player: {:?}, lost", player);
+                            winner = Some(if IslandDefense::team(*class) == BUILDER_TEAM { TITAN_TEAM } else { BUILDER_TEAM });
+                        },
+                        (Some(player), Some(class), 1) =>
+                        {
+                            debug!("Player: {:?}, won", player);
+                            winner = Some(IslandDefense::team(*class));
+                        },
+                        (debug_player, debug_class, debug_value) =>
+                        {
+                            error!("Player[{:?}]: {:?}, Class: {:?}, Value: {:?} are not desired for `flag`", player_index, debug_player, debug_class, debug_value);
+                        },
+                    }
+                },
+                (ISLAND_DEFENSE_FILE, "class", Ok(player_index)) =>
+                {
+                    if game_started
+                    {
+                        continue;
+                    }
+
+                    match (players.get(&player_index), value)
+                    {
+                        (Some(player), class) if class >= 0 && (class as usize) < CLASS_NAMES.len() =>
+                        {
+                            debug!("Player: {:?} is a {} ({})", player, CLASS_NAMES[class as usize], IslandDefense::team(class));
+                            classes.insert(player_index, class);
+                        },
+                        (debug_player, debug_value) =>
+                        {
+                            error!("Player[{:?}]: {:?}, Value: {:?} are not desired for `class`", player_index, debug_player, debug_value);
+                        },
+                    }
+                },
+                (ISLAND_DEFENSE_FILE, "game_start", _) =>
+                {
+                    game_started = true;
+                },
+                _ => {},
+            }
+        }
+
+        let mut builders = Team::new(String::from(BUILDER_TEAM), Vec::new());
+        let mut titans = Team::new(String::from(TITAN_TEAM), Vec::new());
+        for (player_index, class) in classes
+        {
+            let player_role = PlayerRole::new(players[&player_index].clone(), String::from(CLASS_NAMES[class as usize]));
+            if IslandDefense::team(class) == TITAN_TEAM
+            {
+                titans.players.push(player_role);
+            } else
+            {
+                builders.players.push(player_role);
+            }
+        }
+
+        if builders.players.is_empty() || titans.players.is_empty()
+        {
+            bail!("Builders: {} or Titans: {} were empty.", builders.players.len(), titans.players.len());
+        }
+
+        let (outcome, confidence) = match winner
+        {
+            None => (Outcome::Tie, Confidence::Assumed),
+            Some(TITAN_TEAM) if replay.replay_header.duration > TITAN_TIME_LIMIT => (Outcome::Tie, Confidence::Reported),
+            Some(TITAN_TEAM) => (Outcome::Winner(1), Confidence::Reported),
+            Some(_) => (Outcome::Winner(0), Confidence::Reported),
+        };
+
+        Ok(GameResult::new(vec![builders, titans], outcome, confidence))
+    }
+}
+
+/// Turns a result from `IslandDefense` back into what the Island Defense services pass around
+pub fn id_game_result(result: &GameResult) -> Result<IdGameResult>
+{
+    let players = |name: &str| -> Result<Vec<Player>>
+    {
+        let team = result.team(name).ok_or(format!("No {} team", name))?;
+        Ok(team.players.iter().map(|player_role| player_role.player.clone()).collect())
+    };
+
+    let winner = match result.winner().map(|team| team.name.as_str())
+    {
+        Some(BUILDER_TEAM) => IdTeam::Builder,
+        Some(TITAN_TEAM) => IdTeam::Titan,
+        Some(name) => bail!("{} is not an Island Defense team", name),
+        None => IdTeam::Tie,
+    };
+
+    Ok(IdGameResult::new(players(BUILDER_TEAM)?, players(TITAN_TEAM)?, winner))
+}
@@ -0,0 +1,125 @@
+/*
+    Who played what and who won, worked out differently by every map.
+
+    Each map gets a `ResultExtractor` and `extract_game_result` picks the right one from the map path in the game settings,
+    `extract_game_result_or` also takes an extractor for maps that none of them recognise.
+    To support a new map implement `ResultExtractor` for it and add it to `extractors`.
+*/
+
+pub mod island_defense;
+
+use parser::{Replay, GameSettings};
+use pubsub::model::Player;
+
+use ::errors::*;
+
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, new)]
+pub struct PlayerRole
+{
+    pub player: Player,
+    /* Map specific, e.g. "Minion" in Island Defense */
+    pub role: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, new)]
+pub struct Team
+{
+    /* Map specific, e.g. "Builder" in Island Defense */
+    pub name: String,
+    pub players: Vec<PlayerRole>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum Outcome
+{
+    /* Index into `GameResult.teams` */
+    Winner(usize),
+    Tie,
+}
+
+/// How sure the extractor is about the `Outcome`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum Confidence
+{
+    /* The map said who won */
+    Reported,
+    /* The map never said so the extractor fell back on a default */
+    Assumed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, new)]
+pub struct GameResult
+{
+    pub teams: Vec<Team>,
+    pub outcome: Outcome,
+    pub confidence: Confidence,
+}
+
+impl GameResult
+{
+    pub fn team(&self, name: &str) -> Option<&Team>
+    {
+        self.teams.iter().find(|team| team.name == name)
+    }
+
+    /// `None` for a tie
+    pub fn winner(&self) -> Option<&Team>
+    {
+        match self.outcome
+        {
+            Outcome::Winner(index) => self.teams.get(index),
+            Outcome::Tie => None,
+        }
+    }
+}
+
+pub trait ResultExtractor
+{
+    /// Whether this extractor understands the map the game was played on
+    fn handles_map(&self, settings: &GameSettings) -> bool;
+
+    /// # Arguments
+    /// * `player_list` Who played, in the order the players joined the game
+    /// * `replay` The game they played
+    fn extract(&self, player_list: &Vec<Player>, replay: &Replay) -> Result<GameResult>;
+}
+
+/// Every map that results can be extracted from
+pub fn extractors() -> Vec<Box<ResultExtractor>>
+{
+    vec![
+        Box::new(island_defense::IslandDefense),
+    ]
+}
+
+pub fn extractor_for_map(settings: &GameSettings) -> Option<Box<ResultExtractor>>
+{
+    extractors().into_iter()
+        .find(|extractor| extractor.handles_map(settings))
+}
+
+/// Extracts the result with whichever extractor handles the replay's map
+pub fn extract_game_result(player_list: &Vec<Player>, replay: &Replay) -> Result<GameResult>
+{
+    let settings = replay.game_header.game_settings()?;
+    let extractor = extractor_for_map(&settings)
+        .ok_or(format!("No result extractor for map: {}", settings.map_path))?;
+
+    extractor.extract(player_list, replay)
+}
+
+/// Same as `extract_game_result` except maps that no extractor handles are given to `fallback`
+pub fn extract_game_result_or(player_list: &Vec<Player>, replay: &Replay, fallback: &ResultExtractor) -> Result<GameResult>
+{
+    let settings = replay.game_header.game_settings()?;
+    match extractor_for_map(&settings)
+    {
+        Some(extractor) => extractor.extract(player_list, replay),
+        None =>
+        {
+            warn!("No result extractor for map: {}, using the fallback", settings.map_path);
+            fallback.extract(player_list, replay)
+        },
+    }
+}
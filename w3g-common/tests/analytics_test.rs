@@ -0,0 +1,333 @@
+extern crate w3g_common;
+extern crate serde_json;
+
+use w3g_common::parser::{Action, OrderType, GameObject, Dictionary, SelectionOperation};
+use w3g_common::parser::dictionary::rawcode_to_id;
+use w3g_common::analytics::apm::{ApmOptions, player_apm, player_apm_with_options};
+use w3g_common::analytics::build_order::{BuildOrderKind, build_orders};
+use w3g_common::analytics::heatmap::{Heatmap, HeatmapBounds, HeatmapFilter, player_ids_with_role, player_ids_in_team};
+use w3g_common::analytics::entity::EntityRegistry;
+use w3g_common::analytics::selection::{SelectionTracker, selected_orders, control_group_usage};
+use w3g_common::result::extract_game_result;
+
+mod common;
+use common::{player_list, push_first_actions, push_tick};
+
+#[test]
+fn test_apm_11151811()
+{
+    let replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let apm = player_apm(&replay);
+
+    assert_eq!(apm.len(), replay.game_header.players.len() + 1);
+    assert_eq!(apm[0].player_id, replay.game_header.replay_saver.player_id);
+
+    for player in apm.iter()
+    {
+        assert!(player.effective_actions <= player.actions);
+        assert!(player.eapm <= player.apm);
+        assert_eq!(player.buckets.iter().map(|bucket| bucket.actions).sum::<u32>(), player.actions);
+        assert_eq!(player.buckets.iter().map(|bucket| bucket.duration).sum::<u32>(), player.time_played);
+    }
+    assert!(apm.iter().any(|player| player.apm > 0.0));
+}
+
+#[test]
+fn test_eapm_discounts_spam()
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let player_id = replay.game_header.replay_saver.player_id;
+    let before = player_apm(&replay)[0].clone();
+
+    push_first_actions(&mut replay, player_id, vec![
+        Action::SelectGroup { group_number: 9, unknown: 0 },
+        Action::SelectGroup { group_number: 9, unknown: 0 },
+        Action::EnterBuildingSubMenu(),
+        Action::SyncStoredInteger { file: String::from("ID.D"), group: String::from("test"), key: String::from("0"), value: 0 },
+    ]);
+
+    let after = player_apm(&replay)[0].clone();
+    assert_eq!(after.actions, before.actions + 3);
+    assert_eq!(after.effective_actions, before.effective_actions + 1);
+    assert_eq!(after.buckets[0].actions, before.buckets[0].actions + 3);
+}
+
+#[test]
+fn test_apm_buckets()
+{
+    let replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let minutes = player_apm(&replay);
+    let five_minutes = player_apm_with_options(&replay, ApmOptions::new(5 * 60 * 1000, 500));
+
+    for (minutes, five_minutes) in minutes.iter().zip(five_minutes.iter())
+    {
+        assert_eq!(minutes.actions, five_minutes.actions);
+        assert_eq!(five_minutes.buckets.len(), (minutes.buckets.len() + 4) / 5);
+        assert_eq!(five_minutes.buckets[0].actions, minutes.buckets.iter().take(5).map(|bucket| bucket.actions).sum::<u32>());
+    }
+}
+
+#[test]
+fn test_build_orders_11379705()
+{
+    let replay = w3g_common::parser::extract_replay("resources/11379705.w3g").unwrap();
+    let build_orders = build_orders(&replay, &Dictionary::default());
+
+    assert_eq!(build_orders.len(), replay.game_header.players.len() + 1);
+    assert!(build_orders.iter().any(|build_order| !build_order.entries_of(BuildOrderKind::Construct).is_empty()));
+    assert!(build_orders.iter().any(|build_order| !build_order.entries_of(BuildOrderKind::Train).is_empty()));
+
+    for build_order in build_orders.iter()
+    {
+        assert!(build_order.entries.windows(2).all(|pair| pair[0].time <= pair[1].time));
+        assert!(build_order.entries_of(BuildOrderKind::Construct).iter().all(|entry| entry.location.is_some()));
+    }
+}
+
+/// The -1 object orders carry
+fn unknown_object() -> GameObject
+{
+    GameObject::new(0xFFFF_FFFF, 0xFFFF_FFFF)
+}
+
+#[test]
+fn test_build_order_entries()
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11379705.w3g").unwrap();
+    let player_id = replay.game_header.replay_saver.player_id;
+
+    push_tick(&mut replay, player_id, vec![
+        Action::PointOrder { flags: vec![OrderType::Queue, OrderType::Construct], order_id: rawcode_to_id("hbar").unwrap(), unknown: unknown_object(), x: -512.0, y: 256.0 },
+        Action::SelfOrder { flags: vec![OrderType::Train], order_id: rawcode_to_id("hfoo").unwrap(), unknown: unknown_object() },
+        Action::CancelUnitInQueue { slot_index: 1, unit_id: rawcode_to_id("hfoo").unwrap() },
+        /* A summon, not a rawcode */
+        Action::SelfOrder { flags: vec![OrderType::Construct, OrderType::Summon], order_id: 0x000D_0097, unknown: unknown_object() },
+    ]);
+
+    let build_orders = build_orders(&replay, &Dictionary::default());
+    let entries = &build_orders[0].entries;
+    let entries = &entries[entries.len() - 3..];
+
+    assert_eq!(entries[0].kind, BuildOrderKind::Construct);
+    assert_eq!(entries[0].rawcode, "hbar");
+    assert_eq!(entries[0].name, Some(String::from("Barracks")));
+    assert_eq!(entries[0].location, Some((-512.0, 256.0)));
+    assert!(entries[0].queued);
+
+    assert_eq!(entries[1].kind, BuildOrderKind::Train);
+    assert_eq!(entries[1].name, Some(String::from("Footman")));
+    assert!(!entries[1].queued);
+
+    assert_eq!(entries[2].kind, BuildOrderKind::Cancel);
+    assert_eq!(entries[2].slot_index, Some(1));
+    assert_eq!(entries[2].time, entries[0].time);
+}
+
+#[test]
+fn test_heatmap_points()
+{
+    let mut heatmap = Heatmap::new(HeatmapBounds::new(-100.0, -100.0, 100.0, 100.0), 4, 2).unwrap();
+
+    assert!(heatmap.add_point(-100.0, 100.0));
+    assert!(heatmap.add_point(100.0, -100.0));
+    assert!(heatmap.add_point(10.0, 10.0));
+    assert!(!heatmap.add_point(101.0, 0.0));
+    assert!(!heatmap.add_point(::std::f32::NAN, 0.0));
+
+    assert_eq!(heatmap.get(0, 0), Some(1));
+    assert_eq!(heatmap.get(3, 1), Some(1));
+    assert_eq!(heatmap.get(2, 0), Some(1));
+    assert_eq!(heatmap.get(4, 0), None);
+    assert_eq!(heatmap.total(), 3);
+    assert_eq!(heatmap.outside, 2);
+}
+
+#[test]
+fn test_heatmap_empty_bounds()
+{
+    assert!(Heatmap::new(HeatmapBounds::new(0.0, -100.0, 0.0, 100.0), 4, 2).is_err());
+    assert!(Heatmap::new(HeatmapBounds::new(-100.0, 100.0, 100.0, -100.0), 4, 2).is_err());
+    assert!(Heatmap::new(HeatmapBounds::new(::std::f32::NAN, -100.0, 100.0, 100.0), 4, 2).is_err());
+}
+
+#[test]
+fn test_heatmap_11151811()
+{
+    let replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+
+    let mut everything = Heatmap::for_replay(&replay, 64, 64).unwrap();
+    everything.add_replay(&replay, &HeatmapFilter::default());
+
+    let mut structures = Heatmap::for_replay(&replay, 64, 64).unwrap();
+    structures.add_replay(&replay, &HeatmapFilter::new(true, None, 0, None));
+
+    assert_eq!(everything.bounds.max_x, 174.0 * 128.0 / 2.0);
+    assert!(structures.total() > 0);
+    assert!(structures.total() < everything.total());
+
+    let mut early = Heatmap::for_replay(&replay, 64, 64).unwrap();
+    early.add_replay(&replay, &HeatmapFilter::new(true, None, 0, Some(5 * 60 * 1000)));
+    assert!(early.total() < structures.total());
+
+    /* Adding the same replay twice is the same as doubling it */
+    let mut twice = structures.clone();
+    twice.add_replay(&replay, &HeatmapFilter::new(true, None, 0, None));
+    assert_eq!(twice.total(), structures.total() * 2);
+
+    let json = structures.to_json().unwrap();
+    assert_eq!(serde_json::from_str::<Heatmap>(&json).unwrap(), structures);
+}
+
+#[test]
+fn test_heatmap_roles()
+{
+    let replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let players = player_list(&replay);
+
+    let result = extract_game_result(&players, &replay).unwrap();
+    let titans = player_ids_with_role(&replay, &result, "Titan");
+    let builders = player_ids_in_team(&replay, &result, "Builder");
+    /* Kaltecp, in slot 10 */
+    assert_eq!(titans, vec![1]);
+    assert_eq!(builders.len(), players.len() - 1);
+    assert!(!builders.contains(&1));
+
+    let mut titan = Heatmap::for_replay(&replay, 32, 32).unwrap();
+    titan.add_replay(&replay, &HeatmapFilter::new(false, Some(titans), 0, None));
+    let mut everyone = Heatmap::for_replay(&replay, 32, 32).unwrap();
+    everyone.add_replay(&replay, &HeatmapFilter::default());
+
+    assert!(titan.total() > 0);
+    assert!(titan.total() < everyone.total());
+}
+
+fn assert_png(png: &[u8], width: u32, height: u32)
+{
+    let read_u32 = |offset: usize| png[offset..offset + 4].iter().fold(0u32, |value, byte| (value << 8) | *byte as u32);
+
+    assert_eq!(&png[..8], &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n']);
+    assert_eq!(&png[12..16], b"IHDR");
+    assert_eq!(read_u32(16), width);
+    assert_eq!(read_u32(20), height);
+    assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+}
+
+#[test]
+fn test_heatmap_png()
+{
+    let replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let mut heatmap = Heatmap::for_replay(&replay, 48, 32).unwrap();
+    heatmap.add_replay(&replay, &HeatmapFilter::new(true, None, 0, None));
+
+    assert_png(&heatmap.to_png(1).unwrap(), 48, 32);
+    assert_png(&heatmap.to_png(4).unwrap(), 48 * 4, 32 * 4);
+}
+
+fn object(id: u32) -> GameObject
+{
+    GameObject::new(id, id)
+}
+
+#[test]
+fn test_selection_tracker()
+{
+    let mut tracker = SelectionTracker::new();
+    assert!(tracker.player(1).is_none());
+
+    tracker.apply(1, &Action::ChangeSelection { select_mode: SelectionOperation::Add, targets: vec![object(1), object(2)] });
+    tracker.apply(1, &Action::AssignGroup { group_number: 1, targets: vec![object(1), object(2)] });
+    tracker.apply(1, &Action::ChangeSelection { select_mode: SelectionOperation::Remove, targets: vec![object(1)] });
+    tracker.apply(1, &Action::ChangeSelection { select_mode: SelectionOperation::Add, targets: vec![object(3), object(2)] });
+    assert_eq!(tracker.player(1).unwrap().selection, vec![object(2), object(3)]);
+
+    tracker.apply(1, &Action::SelectSubGroup { item_id: 0x68666F6F, target: object(3) });
+    assert_eq!(tracker.player(1).unwrap().subgroup.unwrap().target, object(3));
+    tracker.apply(1, &Action::PreSubSelection());
+    assert!(tracker.player(1).unwrap().subgroup.is_none());
+
+    tracker.apply(1, &Action::SelectGroup { group_number: 1, unknown: 0 });
+    tracker.apply(1, &Action::SelectGroup { group_number: 1, unknown: 0 });
+    tracker.apply(1, &Action::SelectGroup { group_number: 2, unknown: 0 });
+    /* Other players don't share a selection */
+    tracker.apply(2, &Action::ChangeSelection { select_mode: SelectionOperation::Add, targets: vec![object(4)] });
+
+    let player = tracker.player(1).unwrap();
+    assert!(player.selection.is_empty());
+    assert_eq!(player.groups[&1], vec![object(1), object(2)]);
+    assert_eq!(player.usage[&1].assigned, 1);
+    assert_eq!(player.usage[&1].selected, 2);
+    assert_eq!(player.usage[&1].reselected, 1);
+    assert_eq!(player.usage[&1].size, 2);
+    assert_eq!(player.usage[&2].selected, 1);
+    assert_eq!(tracker.player(2).unwrap().selection, vec![object(4)]);
+}
+
+#[test]
+fn test_selected_orders_11379705()
+{
+    let replay = w3g_common::parser::extract_replay("resources/11379705.w3g").unwrap();
+    let orders = selected_orders(&replay);
+
+    assert!(orders.iter().all(|order| order.action.order_id().is_some()));
+    assert!(orders.iter().filter(|order| !order.selection.is_empty()).count() > orders.len() / 2);
+
+    let usage = control_group_usage(&replay);
+    assert_eq!(usage.len(), replay.game_header.players.len() + 1);
+    assert!(usage.iter().any(|player| player.groups.iter().any(|group| group.selected > 0)));
+}
+
+#[test]
+fn test_entity_registry()
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11379705.w3g").unwrap();
+    let player_id = replay.game_header.replay_saver.player_id;
+    let titan = GameObject::new(0x7FFF_0001, 0x7FFF_0001);
+    let enemy = GameObject::new(0x7FFF_0002, 0x7FFF_0002);
+    let titan_type = rawcode_to_id("E000").unwrap();
+
+    /* Someone else clicking on the titan to have a look doesn't make it theirs */
+    let other_player_id = replay.game_header.players[0].player_id;
+    assert_ne!(other_player_id, player_id);
+    push_tick(&mut replay, other_player_id, vec![
+        Action::ChangeSelection { select_mode: SelectionOperation::Add, targets: vec![titan] },
+    ]);
+    push_tick(&mut replay, player_id, vec![
+        Action::ChangeSelection { select_mode: SelectionOperation::Add, targets: vec![titan] },
+        Action::SelectSubGroup { item_id: titan_type, target: titan },
+        Action::ObjectOrder { flags: vec![], order_id: 0x000D_000F, unknown: unknown_object(), x: 0.0, y: 0.0, target: enemy },
+    ]);
+    push_tick(&mut replay, player_id, vec![
+        Action::PointOrder { flags: vec![], order_id: 0x000D_0012, unknown: unknown_object(), x: 64.0, y: 64.0 },
+    ]);
+
+    let registry = EntityRegistry::from_replay(&replay);
+    assert!(!registry.is_empty());
+
+    let entity = registry.get(&titan).unwrap();
+    assert_eq!(entity.owner, Some(player_id));
+    assert_eq!(entity.unit_type, Some(titan_type));
+    assert_eq!(entity.last_seen, entity.first_seen + 200);
+    assert_eq!(entity.orders.iter().map(|order| order.action.order_id().unwrap()).collect::<Vec<_>>(), vec![0x000D_000F, 0x000D_0012]);
+    assert_eq!(registry.of_type(titan_type).len(), 1);
+
+    /* Being attacked doesn't make the enemy ours */
+    let enemy = registry.get(&enemy).unwrap();
+    assert_eq!(enemy.owner, None);
+    assert!(enemy.orders.is_empty());
+    assert!(registry.get(&unknown_object()).is_none());
+}
+
+#[test]
+fn test_entity_registry_11379705()
+{
+    let replay = w3g_common::parser::extract_replay("resources/11379705.w3g").unwrap();
+    let registry = EntityRegistry::from_replay(&replay);
+    let entities = registry.entities();
+
+    assert_eq!(entities.len(), registry.len());
+    assert!(entities.windows(2).all(|pair| pair[0].first_seen <= pair[1].first_seen));
+    assert!(entities.iter().all(|entity| entity.first_seen <= entity.last_seen));
+    assert!(entities.iter().any(|entity| entity.unit_type.is_some()));
+    assert!(entities.iter().any(|entity| !entity.orders.is_empty()));
+    assert!(!registry.owned_by(replay.game_header.replay_saver.player_id).is_empty());
+}
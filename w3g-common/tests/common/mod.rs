@@ -0,0 +1,49 @@
+/*
+    Fixtures shared by the integration tests, pulled in with `mod common;`.
+    Not every test file uses every helper.
+*/
+#![allow(dead_code)]
+
+use w3g_common::parser::{Replay, ReplayBlock, Action, Command};
+use w3g_common::pubsub::model::Player;
+
+
+/// The players as the website would list them, in the order they joined
+pub fn player_list(replay: &Replay) -> Vec<Player>
+{
+    let mut players = Some(&replay.game_header.replay_saver).into_iter()
+        .chain(replay.game_header.players.iter())
+        .collect::<Vec<_>>();
+    players.sort_by_key(|player| player.player_id);
+
+    players.into_iter()
+        .map(|player| Player::new(player.player_name.as_str(), "USEast"))
+        .collect()
+}
+
+/// Adds a command to the first tick, i.e. at the start of the game
+pub fn push_first_actions(replay: &mut Replay, player_id: u8, actions: Vec<Action>)
+{
+    tick_commands(replay.replay_blocks.iter_mut()).push(Command { player_id, num_bytes: 0, actions });
+}
+
+/// Adds a command to the last tick, i.e. at the end of the game
+pub fn push_actions(replay: &mut Replay, player_id: u8, actions: Vec<Action>)
+{
+    tick_commands(replay.replay_blocks.iter_mut().rev()).push(Command { player_id, num_bytes: 0, actions });
+}
+
+/// Adds a command in a tick of its own, 100ms after everything else
+pub fn push_tick(replay: &mut Replay, player_id: u8, actions: Vec<Action>)
+{
+    replay.replay_blocks.push(ReplayBlock::Tick { num_bytes: 0, time_increment: 100, commands: vec![Command { player_id, num_bytes: 0, actions }] });
+}
+
+fn tick_commands<'a, I>(blocks: I) -> &'a mut Vec<Command>
+    where I: Iterator<Item = &'a mut ReplayBlock>
+{
+    blocks
+        .filter_map(|block| match block { ReplayBlock::Tick { commands, .. } => Some(commands), _ => None })
+        .next()
+        .expect("The replay has no ticks")
+}
@@ -4,11 +4,37 @@ extern crate serde;
 extern crate serde_json;
 extern crate rmp_serde;
 extern crate bincode;
+extern crate byteorder;
+extern crate libflate;
 
-use w3g_common::parser::Replay;
+use w3g_common::parser::{Replay, ReplayBlock, ReplayReader, ParseOptions, ParseMode, ParseWarning, Action, Command};
+use w3g_common::parser::{ReforgedMetadata, ReforgedPlayerMetadata, REFORGED_VERSION_NUMBER, LEGACY_VERSION_STRING};
+use w3g_common::parser::{GameSpeed, Visibility, ObserverMode};
+use w3g_common::parser::ParseError;
+use w3g_common::parser::TimelineEvent;
+use w3g_common::parser::{ReplayData, ReplayBlockView, ActionView};
+use w3g_common::parser::Dictionary;
+use w3g_common::parser::ChatChannel;
+use w3g_common::parser::{LeaveReason, LeaveResult};
+use w3g_common::parser::{GameCacheType, GameCacheValue, GameCacheChange, GameCacheKey};
+use w3g_common::parser::{W3mmdFlag, W3mmdValue};
+use w3g_common::parser::{StringEncoding, Codepage};
+use w3g_common::parser::{SlotStatus, SlotController, SlotTeam, Race, PlayerColor, AiStrength};
+use w3g_common::parser::dictionary::{id_to_rawcode, rawcode_to_id};
+use w3g_common::errors::ErrorKind;
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{Cursor, Read, Write};
 
 use serde::{Deserialize, Serialize};
 
+mod common;
+use common::push_actions;
+
+use byteorder::{ByteOrder, LittleEndian};
+use libflate::zlib::{Decoder, Encoder};
+
 #[test]
 fn test_smoke_11379705() {
     // v 1.30
@@ -72,4 +98,1119 @@ fn test_replay_to_bincode()
     assert_eq!(original_replay, deserialized);
     println!("(bincode) replay size: {}", serialized.len());
 }
- 
\ No newline at end of file
+ 
+#[test]
+fn test_reader_blocks_11151811()
+{
+    let file = File::open("resources/11151811.w3g").unwrap();
+    let mut reader = ReplayReader::new(file).unwrap();
+    let blocks = reader.by_ref().collect::<Result<Vec<ReplayBlock>, _>>().unwrap();
+
+    let (mut ticks, mut commands, mut game_time, mut chats, mut leaves) = (0, 0, 0, 0, 0);
+    for block in blocks.iter()
+    {
+        match block
+        {
+            ReplayBlock::Tick { commands: tick_commands, time_increment, .. } | ReplayBlock::TickPreOverflow { commands: tick_commands, time_increment, .. } =>
+            {
+                ticks += 1;
+                commands += tick_commands.len();
+                game_time += *time_increment as u32;
+            },
+            ReplayBlock::PlayerChat { .. } => chats += 1,
+            ReplayBlock::LeaveGame { .. } => leaves += 1,
+            _ => {},
+        }
+    }
+
+    assert_eq!(blocks.len(), 9314);
+    assert_eq!(ticks, 9087);
+    assert_eq!(commands, 9028);
+    assert_eq!(game_time, 908500);
+    assert_eq!(chats, 213);
+    assert_eq!(leaves, 11);
+
+    let replay = reader.into_replay(blocks);
+    assert_eq!(replay.replay_blocks.len(), 9314);
+}
+
+#[test]
+fn test_reader_stops_early()
+{
+    let file = File::open("resources/11379705.w3g").unwrap();
+    let reader = ReplayReader::new(file).unwrap();
+
+    assert_eq!(reader.game_header.game_record.record_id, 0x19);
+
+    let first_tick = reader
+        .map(|block| block.unwrap())
+        .find(|block| match block { ReplayBlock::Tick { .. } => true, _ => false });
+
+    assert!(first_tick.is_some());
+}
+
+fn rewrite(replay: &Replay) -> Replay
+{
+    let mut written = Vec::new();
+    w3g_common::parser::write_replay(replay, &mut written).unwrap();
+
+    w3g_common::parser::parse_replay(&mut Cursor::new(written)).unwrap()
+}
+
+fn assert_round_trip_replay(original_replay: Replay)
+{
+    let mut written = Vec::new();
+    w3g_common::parser::write_replay(&original_replay, &mut written).unwrap();
+    /* Parsed strictly so the header's crc is checked */
+    let rewritten_replay = w3g_common::parser::parse_replay(&mut Cursor::new(&written[..])).unwrap();
+
+    assert_eq!(rewritten_replay.compressed_size as usize, written.len());
+    assert_eq!(original_replay.without_compression(), rewritten_replay.without_compression());
+}
+
+fn assert_round_trip(path: &str)
+{
+    assert_round_trip_replay(w3g_common::parser::extract_replay(path).unwrap());
+}
+
+#[test]
+fn test_round_trip_11379705()
+{
+    assert_round_trip("resources/11379705.w3g");
+}
+
+#[test]
+fn test_round_trip_11151616()
+{
+    assert_round_trip("resources/11151616.w3g");
+}
+
+#[test]
+fn test_round_trip_11151801()
+{
+    assert_round_trip("resources/11151801.w3g");
+}
+
+#[test]
+fn test_round_trip_11151811()
+{
+    assert_round_trip("resources/11151811.w3g");
+}
+
+#[test]
+fn test_player_record_unknown_round_trip()
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    assert_eq!(replay.game_header.replay_saver.unknown, None);
+    assert!(replay.game_header.players.iter().all(|player| player.unknown.is_some()));
+
+    replay.game_header.players[0].unknown = Some(0x12345678);
+    assert_eq!(rewrite(&replay).game_header.players[0].unknown, Some(0x12345678));
+    assert_round_trip_replay(replay);
+}
+
+/// Flips a byte inside of the stored crc for the header and the first data block
+fn corrupted_checksums(path: &str) -> Vec<u8>
+{
+    let mut raw = Vec::new();
+    File::open(path).unwrap().read_to_end(&mut raw).unwrap();
+
+    /* header crc is the last dword of the 0x44 byte header */
+    raw[0x40] = raw[0x40] ^ 0xFF;
+    /* first block's crc follows its 2 words of sizes */
+    raw[0x44 + 4] = raw[0x44 + 4] ^ 0xFF;
+
+    raw
+}
+
+#[test]
+fn test_strict_rejects_bad_checksum()
+{
+    let raw = corrupted_checksums("resources/11151801.w3g");
+
+    assert!(w3g_common::parser::parse_replay(&mut Cursor::new(raw)).is_err());
+}
+
+#[test]
+fn test_lenient_records_bad_checksum()
+{
+    let raw = corrupted_checksums("resources/11151801.w3g");
+
+    let options = ParseOptions::new(ParseMode::Lenient);
+    let replay = w3g_common::parser::parse_replay_with_options(&mut Cursor::new(raw), options).unwrap();
+
+    assert_eq!(replay.warnings.len(), 2);
+    match (&replay.warnings[0], &replay.warnings[1])
+    {
+        (ParseWarning::HeaderChecksumMismatch { .. }, ParseWarning::BlockChecksumMismatch { block_index: 0, .. }) => {},
+        warnings => panic!("Unexpected warnings: {:?}", warnings),
+    }
+}
+
+/// Commands of the first tick that has any
+fn first_commands(replay: &mut Replay) -> &mut Vec<Command>
+{
+    replay.replay_blocks.iter_mut()
+        .filter_map(|block| match block { ReplayBlock::Tick { commands, .. } => Some(commands), _ => None })
+        .find(|commands| !commands.is_empty())
+        .unwrap()
+}
+
+#[test]
+fn test_reforged_round_trip()
+{
+    /* There's no Reforged replay in resources so dress up a 1.30 one with the Reforged only records */
+    let mut replay = w3g_common::parser::extract_replay("resources/11379705.w3g").unwrap();
+    replay.replay_header.version_number = REFORGED_VERSION_NUMBER;
+    assert!(replay.replay_header.is_reforged());
+
+    replay.game_header.reforged_metadata = vec![
+        ReforgedMetadata::Player(ReforgedPlayerMetadata {
+            player_id: 1,
+            battle_tag: String::from("Grumble#1234"),
+            clan: String::new(),
+            portrait: String::from("p042"),
+            team: 0,
+            unknown: String::new(),
+        }),
+        ReforgedMetadata::Unknown { subtype: 0x04, data: vec![0x08, 0x01] },
+    ];
+
+    first_commands(&mut replay)[0].actions.extend(vec![
+        Action::MouseAction { event: 1, x: -512.0, y: 1024.5, button: 2 },
+        Action::W3Api { command_id: 3, data: 4, buffer: vec![5, 6, 7] },
+        Action::BlzSync { identifier: String::from("id"), value: String::from("value"), unknown: 8 },
+        Action::CommandFrame { unknown_a: 1, unknown_b: 2, event_id: 3, value: 0.5, text: String::from("frame") },
+        Action::Unknown7A { unknown: vec![0x7A; 20] },
+        Action::Unknown7B { unknown: vec![0x7B; 16] },
+        Action::UnknownA0 { unknown: vec![0xA0; 14] },
+        Action::UnknownA1 { unknown: vec![0xA1; 9] },
+    ]);
+
+    let mut rewritten_replay = rewrite(&replay);
+
+    assert_eq!(replay.replay_header.version_number, rewritten_replay.replay_header.version_number);
+    assert_eq!(replay.game_header, rewritten_replay.game_header);
+    /* `num_bytes` of the block & command were recomputed so only compare the actions */
+    assert_eq!(first_commands(&mut replay)[0].actions, first_commands(&mut rewritten_replay)[0].actions);
+    assert_eq!(replay.replay_blocks.len(), rewritten_replay.replay_blocks.len());
+}
+
+#[test]
+fn test_reforged_block_checksum()
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11379705.w3g").unwrap();
+    replay.replay_header.version_number = REFORGED_VERSION_NUMBER;
+    let mut written = Vec::new();
+    w3g_common::parser::write_replay(&replay, &mut written).unwrap();
+
+    /* first block's crc follows its 2 dwords of sizes */
+    written[0x44 + 8] = written[0x44 + 8] ^ 0xFF;
+    assert!(w3g_common::parser::parse_replay(&mut Cursor::new(&written[..])).is_err());
+
+    let options = ParseOptions::new(ParseMode::Lenient);
+    let lenient_replay = w3g_common::parser::parse_replay_with_options(&mut Cursor::new(&written[..]), options).unwrap();
+    match &lenient_replay.warnings[..]
+    {
+        [ParseWarning::BlockChecksumMismatch { block_index: 0, .. }] => {},
+        warnings => panic!("Unexpected warnings: {:?}", warnings),
+    }
+}
+
+#[test]
+fn test_legacy_header_round_trip()
+{
+    /* There's no pre 1.07 replay in resources so only the header is swapped out */
+    let mut replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    replay.header_version = 0;
+    replay.file_offset = 0x40;
+    replay.replay_header.version_string = String::from(LEGACY_VERSION_STRING);
+
+    assert_round_trip_replay(replay);
+}
+
+/// 11151811 dressed up as a `header_version` 0 replay of `version_number` with the actions that patch had
+fn legacy_replay(version_number: u32) -> Replay
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    replay.header_version = 0;
+    replay.file_offset = 0x40;
+    replay.replay_header.version_string = String::from(LEGACY_VERSION_STRING);
+    replay.replay_header.version_number = version_number;
+
+    for block in replay.replay_blocks.iter_mut()
+    {
+        if let ReplayBlock::Tick { commands, .. } = block
+        {
+            for command in commands.iter_mut()
+            {
+                for action in command.actions.iter_mut()
+                {
+                    if let Action::SelectSubGroup { .. } = action
+                    {
+                        *action = Action::SelectSubGroupIndex { subgroup: 1 };
+                    }
+                }
+            }
+        }
+    }
+
+    replay
+}
+
+#[test]
+fn test_legacy_actions_round_trip()
+{
+    let replay = legacy_replay(6);
+
+    /* Orders lose their unknown object ids before 1.07 so the first write isn't lossless, after that it should be stable */
+    let legacy_replay = rewrite(&replay);
+    assert_eq!(legacy_replay.header_version, 0);
+    assert_eq!(legacy_replay.replay_header.version_number, 6);
+    assert_eq!(legacy_replay.replay_blocks.len(), replay.replay_blocks.len());
+
+    assert_round_trip_replay(legacy_replay);
+}
+
+/// Inflates every block of a replay from before Reforged
+fn decompressed_data(raw: &[u8]) -> Vec<u8>
+{
+    let file_offset = LittleEndian::read_u32(&raw[0x1C..]) as usize;
+    let number_of_compressed_blocks = LittleEndian::read_u32(&raw[0x2C..]);
+
+    let mut data = Vec::new();
+    let mut offset = file_offset;
+    for _ in 0..number_of_compressed_blocks
+    {
+        /* compressed size, decompressed size & crc */
+        let compressed_size = LittleEndian::read_u16(&raw[offset..]) as usize;
+        offset = offset + 8;
+        Decoder::new(&raw[offset..offset + compressed_size]).unwrap().read_to_end(&mut data).unwrap();
+        offset = offset + compressed_size;
+    }
+
+    data
+}
+
+/// Compresses `data` back into the blocks of `raw`, leaving the checksums zeroed so it needs `ParseMode::Lenient`
+fn recompressed(raw: &[u8], data: &[u8]) -> Vec<u8>
+{
+    let file_offset = LittleEndian::read_u32(&raw[0x1C..]) as usize;
+
+    let mut recompressed = raw[..file_offset].to_vec();
+    for chunk in data.chunks(8192)
+    {
+        let mut encoder = Encoder::new(Vec::new()).unwrap();
+        encoder.write_all(chunk).unwrap();
+        let compressed = encoder.finish().into_result().unwrap();
+
+        let mut sizes = [0u8; 8];
+        LittleEndian::write_u16(&mut sizes[0..], compressed.len() as u16);
+        LittleEndian::write_u16(&mut sizes[2..], chunk.len() as u16);
+        recompressed.extend(&sizes);
+        recompressed.extend(compressed);
+    }
+    let compressed_size = recompressed.len() as u32;
+    LittleEndian::write_u32(&mut recompressed[0x20..], compressed_size);
+
+    recompressed
+}
+
+/// Writes `block` first, then shrinks its `num_bytes` (found by `pattern`) to `num_bytes`, which has to be rejected by both parsers
+fn assert_block_size_rejected(block: ReplayBlock, pattern: &[u8], num_bytes: u8)
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    replay.replay_blocks.insert(0, block);
+    let mut written = Vec::new();
+    w3g_common::parser::write_replay(&replay, &mut written).unwrap();
+
+    let mut data = decompressed_data(&written);
+    let offset = data.windows(pattern.len()).position(|window| window == pattern).unwrap();
+    data[offset + pattern.len() - 2] = num_bytes;
+    let corrupted = recompressed(&written, &data);
+
+    let assert_block_size = |error: w3g_common::errors::Error| match error.kind()
+    {
+        ErrorKind::Parse(ParseError::InvalidValue { context, value, .. }) =>
+        {
+            assert_eq!(*value, num_bytes as u64);
+            assert_eq!(context.block_id, Some(pattern[0]));
+        },
+        kind => panic!("Unexpected error: {:?}", kind),
+    };
+
+    let options = ParseOptions::new(ParseMode::Lenient);
+    assert_block_size(w3g_common::parser::parse_replay_with_options(&mut Cursor::new(&corrupted[..]), options).unwrap_err());
+
+    let data = w3g_common::parser::parse_replay_bytes_with_options(&corrupted, options).unwrap();
+    assert_block_size(data.blocks().next().unwrap().unwrap_err());
+}
+
+#[test]
+fn test_block_size_too_small()
+{
+    /* 1 byte can't hold the time increment of a tick */
+    assert_block_size_rejected(ReplayBlock::Tick { num_bytes: 0, time_increment: 0x1234, commands: vec![] }, &[0x1F, 2, 0], 1);
+    /* 5 bytes can't hold the flags, chat mode & terminator of a message that isn't from the lobby */
+    let chat = ReplayBlock::PlayerChat { player_id: 2, num_bytes: 0, flags: 0x20, chat_mode: 0, message: String::from("gl"), raw_message: None };
+    assert_block_size_rejected(chat, &[0x20, 2, 8, 0], 5);
+}
+
+#[test]
+fn test_legacy_slot_records()
+{
+    /* `ai_strength` came in 1.03 and `handicap` in 1.07 */
+    for &(version_number, slot_size) in [(2, 7), (6, 8)].iter()
+    {
+        let replay = legacy_replay(version_number);
+        let slots = &replay.game_header.game_record.slot_records;
+
+        let mut expected = vec![0x19];
+        expected.write_all(&[(7 + slots.len() * slot_size) as u8, 0, slots.len() as u8]).unwrap();
+        for slot in slots.iter()
+        {
+            let bytes = [slot.player_id, slot.download_percent, slot.slot_status, slot.player_flag, slot.team_number, slot.color, slot.race, slot.ai_strength];
+            expected.write_all(&bytes[..slot_size]).unwrap();
+        }
+
+        let mut written = Vec::new();
+        w3g_common::parser::write_replay(&replay, &mut written).unwrap();
+        let data = decompressed_data(&written);
+        assert!(data.windows(expected.len()).any(|window| window == &expected[..]), "No {} byte slot records for {}", slot_size, version_number);
+
+        let legacy_replay = w3g_common::parser::parse_replay(&mut Cursor::new(written)).unwrap();
+        let legacy_slots = &legacy_replay.game_header.game_record.slot_records;
+        assert_eq!(legacy_replay.game_header.game_record.num_data_bytes as usize, 7 + slots.len() * slot_size);
+        assert_eq!(legacy_replay.replay_blocks.len(), replay.replay_blocks.len());
+        for (slot, legacy_slot) in slots.iter().zip(legacy_slots.iter())
+        {
+            assert_eq!((legacy_slot.player_id, legacy_slot.team_number, legacy_slot.color, legacy_slot.race), (slot.player_id, slot.team_number, slot.color, slot.race));
+            assert_eq!(legacy_slot.handicap, 100);
+        }
+    }
+}
+
+#[test]
+fn test_game_settings_11151811()
+{
+    let replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let settings = replay.game_header.game_settings().unwrap();
+
+    assert_eq!(settings.speed, GameSpeed::Fast);
+    assert_eq!(settings.visibility, vec![Visibility::Default]);
+    assert_eq!(settings.observers, ObserverMode::Off);
+    assert!(settings.teams_together);
+    assert!(settings.fixed_teams);
+    assert!(settings.full_shared_unit_control);
+    assert!(!settings.random_races);
+    assert_eq!((settings.map_width, settings.map_height), (174, 174));
+    assert_eq!(settings.map_path, "Maps\\Download\\IDProt4.0.1.w3x");
+    assert_eq!(settings.map_file_name(), "IDProt4.0.1.w3x");
+    assert_eq!(settings.host_name, "GHost++");
+    assert_eq!(settings.map_sha1.map(|sha1| sha1.len()), Some(20));
+}
+
+#[test]
+fn test_lenient_skips_unknown_action()
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let player_id = first_commands(&mut replay)[0].player_id;
+    first_commands(&mut replay)[0].actions.push(Action::Unknown { id: 0x99, bytes: vec![1, 2, 3] });
+
+    let mut written = Vec::new();
+    w3g_common::parser::write_replay(&replay, &mut written).unwrap();
+
+    assert!(w3g_common::parser::parse_replay(&mut Cursor::new(written.clone())).is_err());
+
+    let options = ParseOptions::new(ParseMode::Lenient);
+    let mut lenient_replay = w3g_common::parser::parse_replay_with_options(&mut Cursor::new(written), options).unwrap();
+
+    assert_eq!(lenient_replay.warnings, vec![ParseWarning::UnknownAction { player_id, action_id: 0x99, skipped_bytes: 4 }]);
+    assert_eq!(first_commands(&mut replay)[0].actions, first_commands(&mut lenient_replay)[0].actions);
+    assert_eq!(replay.replay_blocks.len(), lenient_replay.replay_blocks.len());
+}
+
+fn stored_unit() -> Action
+{
+    Action::SyncStoredUnit {
+        file: String::from("ID.D"),
+        group: String::from("titan"),
+        key: String::from("0"),
+        unit_type: rawcode_to_id("E01D").unwrap(),
+        inventory: Vec::new(),
+        experience: 1200,
+        level_ups: 3,
+        skill_points: 1,
+        proper_name_index: 2,
+        unknown1: 0,
+        base_strength: 30,
+        bonus_strength_per_level: 2.5,
+        base_agility: 20,
+        bonus_move_speed: 0.0,
+        bonus_attack_speed: 0.25,
+        bonus_agility_per_level: 1.5,
+        base_intelligence: 25,
+        bonus_intelligence_per_level: 2.0,
+        abilities: Vec::new(),
+        bonus_health: 100.0,
+        bonus_mana: 50.0,
+        sight_radius_day: 1800.0,
+        unknown2: 0,
+        unknown3: 0,
+        unknown4: 0,
+        unknown5: 0,
+        hotkey_flags: 1,
+    }
+}
+
+#[test]
+fn test_stored_unit_view()
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    first_commands(&mut replay)[0].actions.push(stored_unit());
+
+    let mut written = Vec::new();
+    w3g_common::parser::write_replay(&replay, &mut written).unwrap();
+    let data = w3g_common::parser::parse_replay_bytes(&written).unwrap();
+
+    let mut units = Vec::new();
+    for block in data.blocks()
+    {
+        if let ReplayBlockView::Tick { commands, .. } = block.unwrap()
+        {
+            for action in commands.iter().flat_map(|command| command.actions().unwrap().0)
+            {
+                if let ActionView::SyncStoredUnit { file: Cow::Borrowed(_), .. } = action
+                {
+                    units.push(action.into_action());
+                }
+            }
+        }
+    }
+    assert_eq!(units, vec![stored_unit()]);
+}
+
+/// Every warning from decoding the actions of `data`
+fn view_warnings(data: &ReplayData) -> w3g_common::errors::Result<Vec<ParseWarning>>
+{
+    let mut warnings = Vec::new();
+    for block in data.blocks()
+    {
+        if let ReplayBlockView::Tick { commands, .. } = block?
+        {
+            for command in commands.iter()
+            {
+                warnings.extend(command.actions()?.1);
+            }
+        }
+    }
+
+    Ok(warnings)
+}
+
+#[test]
+fn test_lenient_view_reports_unknown_action()
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let player_id = first_commands(&mut replay)[0].player_id;
+    first_commands(&mut replay)[0].actions.push(Action::Unknown { id: 0x99, bytes: vec![1, 2, 3] });
+
+    let mut written = Vec::new();
+    w3g_common::parser::write_replay(&replay, &mut written).unwrap();
+
+    let data = w3g_common::parser::parse_replay_bytes(&written).unwrap();
+    assert!(view_warnings(&data).is_err());
+
+    let data = w3g_common::parser::parse_replay_bytes_with_options(&written, ParseOptions::new(ParseMode::Lenient)).unwrap();
+    let warnings = view_warnings(&data).unwrap();
+    assert_eq!(warnings, vec![ParseWarning::UnknownAction { player_id, action_id: 0x99, skipped_bytes: 4 }]);
+}
+
+#[test]
+fn test_unknown_action_error_context()
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let player_id = first_commands(&mut replay)[0].player_id;
+    first_commands(&mut replay)[0].actions.push(Action::Unknown { id: 0x99, bytes: vec![1, 2, 3] });
+
+    let mut written = Vec::new();
+    w3g_common::parser::write_replay(&replay, &mut written).unwrap();
+
+    let error = w3g_common::parser::parse_replay(&mut Cursor::new(written)).unwrap_err();
+    match error.kind()
+    {
+        ErrorKind::Parse(ParseError::UnknownAction { context }) =>
+        {
+            assert!(context.offset > 0);
+            assert_eq!(context.block_id, Some(0x1F));
+            assert_eq!(context.player_id, Some(player_id));
+            assert_eq!(context.action_id, Some(0x99));
+        },
+        kind => panic!("Unexpected error: {:?}", kind),
+    }
+}
+
+#[test]
+fn test_truncated_replay_error_context()
+{
+    let mut raw = Vec::new();
+    File::open("resources/11151811.w3g").unwrap().read_to_end(&mut raw).unwrap();
+    let length = raw.len() / 2;
+    raw.truncate(length);
+
+    let error = w3g_common::parser::parse_replay(&mut Cursor::new(raw)).unwrap_err();
+    match error.kind()
+    {
+        ErrorKind::Parse(ParseError::UnexpectedEnd { context }) => assert!(context.block_id.is_some()),
+        kind => panic!("Unexpected error: {:?}", kind),
+    }
+}
+
+#[test]
+fn test_oversized_file_offset()
+{
+    let mut raw = Vec::new();
+    File::open("resources/11151811.w3g").unwrap().read_to_end(&mut raw).unwrap();
+    LittleEndian::write_u32(&mut raw[0x1C..], 0xFFFF_FFF0);
+
+    let error = w3g_common::parser::parse_replay(&mut Cursor::new(raw)).unwrap_err();
+    match error.kind()
+    {
+        ErrorKind::Parse(ParseError::InvalidValue { value, .. }) => assert_eq!(*value, 0xFFFF_FFF0),
+        kind => panic!("Unexpected error: {:?}", kind),
+    }
+}
+
+/// Game time of the last entry in the timeline
+fn end_time(replay: &Replay) -> u32
+{
+    replay.timeline().last().map(|entry| entry.time).unwrap_or(0)
+}
+
+#[test]
+fn test_timeline_11151811()
+{
+    let replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let timeline = replay.timeline();
+
+    assert!(timeline.windows(2).all(|pair| pair[0].time <= pair[1].time));
+
+    let blocks = timeline.iter().filter(|entry| match entry.event { TimelineEvent::Block(_) => true, _ => false }).count();
+    assert_eq!(blocks, replay.replay_blocks.len());
+
+    let actions = timeline.iter().filter(|entry| match entry.event { TimelineEvent::Action { .. } => true, _ => false }).count();
+    let expected_actions = replay.replay_blocks.iter()
+        .map(|block| match block
+        {
+            ReplayBlock::Tick { commands, .. } | ReplayBlock::TickPreOverflow { commands, .. } => commands.iter().map(|command| command.actions.len()).sum(),
+            _ => 0,
+        })
+        .sum::<usize>();
+    assert_eq!(actions, expected_actions);
+
+    /* The header's duration also counts the time before the first tick */
+    let end = end_time(&replay);
+    assert!(end > 0 && end <= replay.replay_header.duration);
+}
+
+#[test]
+fn test_timeline_skips_paused_time()
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let unpaused_end = end_time(&replay);
+
+    /* Pause in the first tick with commands and resume in the last tick */
+    first_commands(&mut replay)[0].actions.push(Action::PauseGame {});
+    let pause_time = replay.timeline().iter().find(|entry| entry.paused).unwrap().time;
+    push_actions(&mut replay, 1, vec![Action::ResumeGame {}]);
+
+    assert!(pause_time < unpaused_end);
+    assert_eq!(end_time(&replay), pause_time);
+}
+
+/// The zero-copy path has to agree with `parse_replay` on everything
+fn assert_views_match(path: &str)
+{
+    let mut raw = Vec::new();
+    File::open(path).unwrap().read_to_end(&mut raw).unwrap();
+
+    let replay = w3g_common::parser::parse_replay(&mut Cursor::new(&raw[..])).unwrap();
+    let data = w3g_common::parser::parse_replay_bytes(&raw).unwrap();
+    assert_eq!(data.replay_header, replay.replay_header);
+    assert_eq!(data.game_header, replay.game_header);
+
+    let views = data.blocks().collect::<w3g_common::errors::Result<Vec<ReplayBlockView>>>().unwrap();
+    assert_eq!(views.len(), replay.replay_blocks.len());
+
+    for (view, block) in views.iter().zip(replay.replay_blocks.iter())
+    {
+        match (view, block)
+        {
+            (ReplayBlockView::Tick { time_increment, commands, .. }, ReplayBlock::Tick { time_increment: expected_time_increment, commands: expected_commands, .. }) |
+            (ReplayBlockView::TickPreOverflow { time_increment, commands, .. }, ReplayBlock::TickPreOverflow { time_increment: expected_time_increment, commands: expected_commands, .. }) =>
+            {
+                assert_eq!(time_increment, expected_time_increment);
+                assert_eq!(commands.len(), expected_commands.len());
+                for (command, expected_command) in commands.iter().zip(expected_commands.iter())
+                {
+                    assert_eq!(command.player_id, expected_command.player_id);
+                    let (actions, warnings) = command.actions().unwrap();
+                    assert!(warnings.is_empty());
+                    let actions = actions.into_iter()
+                        .map(ActionView::into_action)
+                        .collect::<Vec<Action>>();
+                    assert_eq!(actions, expected_command.actions);
+                }
+            },
+            (ReplayBlockView::PlayerChat { player_id, message, .. }, ReplayBlock::PlayerChat { player_id: expected_player_id, message: expected_message, .. }) =>
+            {
+                assert_eq!(player_id, expected_player_id);
+                assert_eq!(message, expected_message);
+            },
+            (ReplayBlockView::Other(view), block) => assert_eq!(view, block),
+            (view, block) => panic!("{:?} doesn't match {:?}", view, block),
+        }
+    }
+}
+
+#[test]
+fn test_views_match_parse_replay()
+{
+    assert_views_match("resources/11379705.w3g");
+    assert_views_match("resources/11151616.w3g");
+    assert_views_match("resources/11151801.w3g");
+    assert_views_match("resources/11151811.w3g");
+}
+
+#[test]
+fn test_action_views_borrow()
+{
+    let mut raw = Vec::new();
+    File::open("resources/11151811.w3g").unwrap().read_to_end(&mut raw).unwrap();
+    let data = w3g_common::parser::parse_replay_bytes(&raw).unwrap();
+
+    /* The map syncs its game cache (W3MMD included), the strings are all UTF-8 so none of them should've been copied */
+    let mut keys = Vec::new();
+    for block in data.blocks()
+    {
+        if let ReplayBlockView::Tick { commands, .. } = block.unwrap()
+        {
+            for action in commands.iter().flat_map(|command| command.actions().unwrap().0)
+            {
+                if let ActionView::SyncStoredInteger { file, group, key, .. } = action
+                {
+                    keys.push(file);
+                    keys.push(group);
+                    keys.push(key);
+                }
+            }
+        }
+    }
+    assert!(keys.contains(&Cow::Borrowed("MMD.Dat")));
+    assert!(keys.iter().all(|key| match key { Cow::Borrowed(_) => true, Cow::Owned(_) => false }));
+}
+
+#[test]
+fn test_parallel_decompression_matches_serial()
+{
+    let options = ParseOptions { parallel_decompression: true, ..ParseOptions::default() };
+
+    for path in ["resources/11379705.w3g", "resources/11151616.w3g", "resources/11151801.w3g", "resources/11151811.w3g"].iter()
+    {
+        let replay = w3g_common::parser::extract_replay(path).unwrap();
+        let parallel_replay = w3g_common::parser::parse_replay_with_options(&mut File::open(path).unwrap(), options).unwrap();
+
+        assert_eq!(parallel_replay, replay);
+    }
+}
+
+fn assert_metadata_matches(path: &str)
+{
+    let replay = w3g_common::parser::extract_replay(path).unwrap();
+    let metadata = w3g_common::parser::extract_replay_metadata(path).unwrap();
+
+    assert_eq!(metadata.replay_header, replay.replay_header);
+    assert_eq!(metadata.game_header, replay.game_header);
+
+    let leave_games = replay.replay_blocks.iter()
+        .filter(|block| match block { ReplayBlock::LeaveGame { .. } => true, _ => false })
+        .collect::<Vec<&ReplayBlock>>();
+    assert!(!leave_games.is_empty());
+    assert_eq!(metadata.leave_games.iter().collect::<Vec<&ReplayBlock>>(), leave_games);
+}
+
+#[test]
+fn test_metadata_matches_parse_replay()
+{
+    assert_metadata_matches("resources/11379705.w3g");
+    assert_metadata_matches("resources/11151616.w3g");
+    assert_metadata_matches("resources/11151801.w3g");
+    assert_metadata_matches("resources/11151811.w3g");
+}
+
+#[test]
+fn test_rawcodes()
+{
+    assert_eq!(rawcode_to_id("hpea"), Some(0x6870_6561));
+    assert_eq!(id_to_rawcode(0x6870_6561), Some(String::from("hpea")));
+    assert_eq!(id_to_rawcode(0x000D_0003), None);
+    assert_eq!(rawcode_to_id("hpeasant"), None);
+}
+
+#[test]
+fn test_dictionary_11151811()
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let mut dictionary = Dictionary::default();
+
+    assert_eq!(dictionary.order_name(0x000D_0003), Some("smart"));
+    assert_eq!(dictionary.order_name(rawcode_to_id("htow").unwrap()), Some("Town Hall"));
+
+    let actions = replay.replay_blocks.drain(..)
+        .flat_map(|block| match block
+        {
+            ReplayBlock::Tick { commands, .. } => commands.into_iter().flat_map(|command| command.actions.into_iter()).collect(),
+            _ => Vec::new(),
+        })
+        .collect::<Vec<Action>>();
+    assert!(actions.iter().any(|action| action.order_name(&dictionary) == Some("attack")));
+
+    /* Island Defense's objects are all custom so they need the map's data file */
+    let action = actions.iter().find(|action| action.object_rawcode() == Some(String::from("h00X"))).unwrap();
+    assert_eq!(action.object_name(&dictionary), None);
+
+    let path = std::env::temp_dir().join("w3g_common_test_dictionary.txt");
+    File::create(&path).unwrap().write_all(b"# Island Defense\n\nh00X = Some Builder\n").unwrap();
+    dictionary.load_objects(path.to_str().unwrap()).unwrap();
+
+    assert_eq!(action.object_name(&dictionary), Some("Some Builder"));
+}
+
+#[test]
+fn test_island_defense_roles_dictionary()
+{
+    let mut dictionary = Dictionary::default();
+    dictionary.load_objects("resources/island_defense_roles.txt").unwrap();
+
+    for file in &["11151616", "11151801", "11151811", "11379705"]
+    {
+        let replay = w3g_common::parser::extract_replay(&format!("resources/{}.w3g", file)).unwrap();
+        let selected = |player_id: u8| replay.replay_blocks.iter()
+            .flat_map(|block| match block
+            {
+                ReplayBlock::Tick { commands, .. } => commands.iter().filter(|command| command.player_id == player_id).collect(),
+                _ => Vec::new(),
+            })
+            .flat_map(|command| command.actions.iter())
+            .filter_map(|action| match *action
+            {
+                Action::SelectSubGroup { item_id, .. } => dictionary.object_name(item_id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        /* Every builder picks a builder & the titan (team 2) controls a titan */
+        for player in replay.players()
+        {
+            let role = if player.team == SlotTeam::Team(1) { "Titan" } else { "Builder" };
+            assert!(selected(player.player_id().unwrap()).contains(&role), "{} {:?} {}", file, player.player_name(), role);
+        }
+    }
+}
+
+#[test]
+fn test_chat_log_11151811()
+{
+    let replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let chat_log = replay.chat_log();
+
+    let chats = replay.replay_blocks.iter().filter(|block| match block { ReplayBlock::PlayerChat { .. } => true, _ => false }).count();
+    assert_eq!(chat_log.len(), chats);
+
+    let first = &chat_log[0];
+    assert_eq!(first.message, "Shortest load by player [Slader] was 20.82 seconds.");
+    assert_eq!(first.channel, ChatChannel::All);
+    assert_eq!(first.sender.as_ref().map(|name| name.as_str()), replay.game_header.player(2).map(|player| player.player_name.as_str()));
+    assert!(first.sender.is_some());
+    assert!(chat_log.windows(2).all(|pair| pair[0].time <= pair[1].time));
+}
+
+#[test]
+fn test_chat_log_channels()
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let sender = replay.game_header.replay_saver.player_id;
+    let recipient = replay.game_header.game_record.slot_records[1].player_id;
+
+    replay.replay_blocks.insert(0, ReplayBlock::PlayerChat { player_id: sender, num_bytes: 0, flags: 0x10, chat_mode: 0, message: String::from("gl hf"), raw_message: None });
+    replay.replay_blocks.push(ReplayBlock::PlayerChat { player_id: sender, num_bytes: 0, flags: 0x20, chat_mode: 0x01, message: String::from("push"), raw_message: None });
+    replay.replay_blocks.push(ReplayBlock::PlayerChat { player_id: sender, num_bytes: 0, flags: 0x20, chat_mode: 0x03 + 1, message: String::from("gg"), raw_message: None });
+
+    let replay = rewrite(&replay);
+    let chat_log = replay.chat_log();
+
+    let first = chat_log.first().unwrap();
+    assert_eq!((first.channel, first.time, first.message.as_str()), (ChatChannel::Lobby, 0, "gl hf"));
+
+    let allies = &chat_log[chat_log.len() - 2];
+    assert_eq!((allies.channel, allies.message.as_str()), (ChatChannel::Allies, "push"));
+
+    let private = chat_log.last().unwrap();
+    assert_eq!(private.channel, ChatChannel::Private { slot: 1 });
+    assert_eq!(private.sender, Some(replay.game_header.replay_saver.player_name.clone()));
+    assert_eq!(private.recipient, replay.game_header.player(recipient).map(|player| player.player_name.clone()));
+    assert!(private.recipient.is_some());
+}
+
+#[test]
+fn test_leave_reasons()
+{
+    assert_eq!(LeaveReason::from_u32(0x0C), LeaveReason::ConnectionClosedByLocal);
+    assert_eq!(LeaveReason::from_u32(0x42), LeaveReason::Unknown(0x42));
+    assert_eq!(LeaveResult::from_u32(0x09), LeaveResult::Won);
+    assert_eq!(LeaveResult::from_u32(0x08), LeaveResult::Lost);
+}
+
+#[test]
+fn test_leave_report_11151811()
+{
+    let replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let report = replay.leave_report();
+
+    assert_eq!(report.len(), 1 + replay.game_header.players.len());
+    assert_eq!(report[0].player_name, replay.game_header.replay_saver.player_name);
+
+    let end = replay.timeline().last().unwrap().time;
+    for player in report.iter()
+    {
+        let leave = player.leave.as_ref().unwrap();
+        assert_eq!((leave.reason, leave.result), (LeaveReason::ConnectionClosedByRemote, LeaveResult::Left));
+        assert!(leave.time > 0 && leave.time <= end);
+    }
+
+    /* The replay saver is the last one to leave */
+    let saver_time = report[0].leave.as_ref().unwrap().time;
+    assert!(report.iter().all(|player| player.leave.as_ref().unwrap().time <= saver_time));
+}
+
+#[test]
+fn test_game_cache_11151811()
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let flag_10 = GameCacheKey { file: "ID.D", group: "flag", key: "10", cache_type: GameCacheType::Integer };
+
+    {
+        let game_cache = replay.game_cache();
+        assert_eq!(game_cache[0].file, "ID.D");
+        assert_eq!(game_cache[0].group, "class");
+        assert_eq!(game_cache[0].change, GameCacheChange::Store(GameCacheValue::Integer(3)));
+        assert!(game_cache.windows(2).all(|pair| pair[0].time <= pair[1].time));
+
+        assert_eq!(replay.game_cache_state().get(&flag_10), Some(&GameCacheValue::Integer(0)));
+    }
+
+    /* Flushed after the map stored it */
+    push_actions(&mut replay, 1, vec![Action::SyncEmptyInteger { file: String::from("ID.D"), group: String::from("flag"), key: String::from("10") }]);
+
+    let state = replay.game_cache_state();
+    assert_eq!(state.get(&flag_10), None);
+    assert!(state.contains_key(&GameCacheKey { key: "9", ..flag_10 }));
+}
+
+#[test]
+fn test_w3mmd_11151811()
+{
+    let replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let w3mmd = replay.w3mmd();
+
+    assert_eq!(w3mmd.players.len(), 11);
+    assert!(w3mmd.warnings.is_empty());
+
+    let player = w3mmd.player(0).unwrap();
+    assert_eq!(player.name, "Demonic_Bread");
+    assert_eq!(player.player_id.and_then(|player_id| replay.game_header.player(player_id)).map(|player| player.player_name.as_str()), Some("Demonic_Bread"));
+    assert_eq!(player.result(), Some(W3mmdFlag::Winner));
+    assert_eq!(w3mmd.player(10).unwrap().result(), Some(W3mmdFlag::Loser));
+}
+
+#[test]
+fn test_w3mmd_messages()
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let messages = vec![
+        "init version 0 1",
+        "DefVarP kills int high leaderboard",
+        "DefVarP accuracy real high track",
+        "DefVarP hero string none none",
+        "VarP 0 kills += 3",
+        "VarP 0 kills -= 1",
+        "VarP 0 accuracy = 0.5",
+        "VarP 0 hero = \"Far\\ Seer\"",
+        "DefEvent kill 2 killer victim {0}\\ killed\\ {1}",
+        "Event kill 0 10",
+        "Event kill 0",
+        "FlagP 0 leaver",
+        "FlagP 0 loser",
+        "Nonsense",
+    ];
+    let sent = replay.w3mmd().messages.len();
+
+    let actions = messages.iter().enumerate()
+        .flat_map(|(id, message)| vec![
+            Action::SyncStoredInteger { file: String::from("MMD.Dat"), group: format!("val:{}", id + 1), key: String::from(*message), value: 0 },
+            Action::SyncStoredInteger { file: String::from("MMD.Dat"), group: format!("chk:{}", id + 1), key: String::from("0"), value: id as i32 },
+        ])
+        .collect();
+    push_actions(&mut replay, 1, actions);
+
+    let w3mmd = replay.w3mmd();
+    assert_eq!(w3mmd.version, Some((0, 1)));
+    assert_eq!(w3mmd.variables.len(), 3);
+    /* The checksums aren't messages */
+    assert_eq!(w3mmd.messages.len(), sent + messages.len());
+    assert_eq!(w3mmd.messages.last().unwrap().checksum, Some(messages.len() as i32 - 1));
+
+    let player = w3mmd.player(0).unwrap();
+    assert_eq!(player.variables.get("kills"), Some(&W3mmdValue::Int(2)));
+    assert_eq!(player.variables.get("accuracy"), Some(&W3mmdValue::Real(0.5)));
+    assert_eq!(player.variables.get("hero"), Some(&W3mmdValue::String(String::from("Far Seer"))));
+    assert_eq!(player.flags, vec![W3mmdFlag::Winner, W3mmdFlag::Leaver, W3mmdFlag::Loser]);
+    assert_eq!(player.result(), Some(W3mmdFlag::Loser));
+
+    assert_eq!(w3mmd.event_definitions[0].argument_names, vec!["killer", "victim"]);
+    assert_eq!(w3mmd.event_definitions[0].format, "{0} killed {1}");
+    assert_eq!(w3mmd.events.len(), 1);
+    assert_eq!(w3mmd.events[0].arguments, vec!["0", "10"]);
+
+    /* The event with a missing argument & the unknown message */
+    assert_eq!(w3mmd.warnings.len(), 2);
+    assert_eq!(w3mmd.warnings[1].message.message, "Nonsense");
+}
+
+#[test]
+fn test_slot_decoding()
+{
+    assert_eq!(SlotStatus::from_u8(0x00), SlotStatus::Open);
+    assert_eq!(SlotStatus::from_u8(0x02), SlotStatus::Used);
+    assert_eq!(SlotController::from_u8(0x01), SlotController::Computer);
+    assert_eq!(Race::from_u8(0x41), vec![Race::Human, Race::Selectable]);
+    assert_eq!(Race::from_u8(0x20), vec![Race::Random]);
+    assert_eq!(PlayerColor::from_u8(9), PlayerColor::LightBlue);
+    assert_eq!(PlayerColor::from_u8(23), PlayerColor::Peanut);
+    assert_eq!(PlayerColor::from_u8(42), PlayerColor::Unknown(42));
+    assert_eq!(AiStrength::from_u8(0x02), AiStrength::Insane);
+
+    /* Observers moved from team 12 to 24 in 1.29 */
+    assert_eq!(SlotTeam::from_u8(12, 26, false), SlotTeam::Observer);
+    assert_eq!(SlotTeam::from_u8(12, 29, false), SlotTeam::Team(12));
+    assert_eq!(SlotTeam::from_u8(24, 29, true), SlotTeam::Referee);
+    assert!(SlotTeam::Referee.is_observer());
+}
+
+#[test]
+fn test_players_11151811()
+{
+    let replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let players = replay.players();
+
+    assert_eq!(players.len(), 1 + replay.game_header.players.len());
+    assert_eq!(players.iter().filter(|player| player.is_replay_saver).count(), 1);
+    assert!(players.iter().all(|player| player.controller == SlotController::Human && player.ai_strength.is_none()));
+
+    /* Slots are in the order the map numbers players, unlike player ids */
+    let w3mmd = replay.w3mmd();
+    for player in players.iter()
+    {
+        assert_eq!(player.player_name(), w3mmd.player(player.slot_index as u32).map(|w3mmd_player| w3mmd_player.name.as_str()));
+    }
+    assert_eq!((players[0].player_id(), players[0].player_name()), (Some(2), Some("Demonic_Bread")));
+
+    let titan = &players[10];
+    assert_eq!((titan.player_id(), titan.team, titan.color), (Some(1), SlotTeam::Team(1), Some(PlayerColor::DarkGreen)));
+    assert_eq!(titan.races, vec![Race::Undead]);
+    assert_eq!(players[0].races, vec![Race::Human]);
+}
+
+#[test]
+fn test_codepages()
+{
+    assert_eq!(Codepage::from_language_id(0x0412), Some(Codepage::Korean));
+    assert_eq!(Codepage::from_language_id(0x0419), Some(Codepage::Cyrillic));
+    assert_eq!(Codepage::from_language_id(0x0404), Some(Codepage::TraditionalChinese));
+    assert_eq!(Codepage::from_language_id(0x0804), Some(Codepage::SimplifiedChinese));
+    assert_eq!(Codepage::from_language_id(0x12F8B0), None);
+
+    assert_eq!(Codepage::Korean.decode(&[0xC7, 0xD1, 0xB1, 0xB9]), "한국");
+    assert_eq!(Codepage::Cyrillic.decode(&[0xCF, 0xF0, 0xE8, 0xE2, 0xE5, 0xF2]), "Привет");
+}
+
+#[test]
+fn test_non_utf8_strings()
+{
+    /* "한국" in CP949 & "Привет" in CP1251 */
+    let korean = vec![0xC7, 0xD1, 0xB1, 0xB9];
+    let cyrillic = vec![0xCF, 0xF0, 0xE8, 0xE2, 0xE5, 0xF2];
+
+    let mut replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    replay.game_header.language_id = 0x0412;
+    replay.game_header.replay_saver.player_name = String::from("한국");
+    replay.game_header.replay_saver.raw_player_name = Some(korean.clone());
+    let sender = replay.game_header.replay_saver.player_id;
+    replay.replay_blocks.insert(0, ReplayBlock::PlayerChat { player_id: sender, num_bytes: 0, flags: 0x10, chat_mode: 0, message: String::from("Привет"), raw_message: Some(cyrillic.clone()) });
+
+    let mut written = Vec::new();
+    w3g_common::parser::write_replay(&replay, &mut written).unwrap();
+
+    let parse = |string_encoding: StringEncoding|
+    {
+        let options = ParseOptions { string_encoding: string_encoding, ..ParseOptions::default() };
+        w3g_common::parser::parse_replay_with_options(&mut Cursor::new(&written), options)
+    };
+    let first_chat = |replay: &Replay| match replay.replay_blocks.iter().find(|block| match block { ReplayBlock::PlayerChat { .. } => true, _ => false })
+    {
+        Some(ReplayBlock::PlayerChat { message, raw_message, .. }) => (message.clone(), raw_message.clone()),
+        _ => unreachable!(),
+    };
+
+    match parse(StringEncoding::Strict).unwrap_err().kind()
+    {
+        ErrorKind::Parse(ParseError::InvalidUtf8 { context }) => assert_eq!(context.block_id, None),
+        error => panic!("{:?} is not InvalidUtf8", error),
+    }
+
+    let lossy = parse(StringEncoding::Lossy).unwrap();
+    assert!(lossy.game_header.replay_saver.player_name.contains('\u{FFFD}'));
+    assert_eq!(lossy.game_header.replay_saver.raw_player_name, Some(korean.clone()));
+
+    /* The replay saver is read before the language */
+    let mut guessed = parse(StringEncoding::Guess).unwrap();
+    assert_eq!(guessed.game_header.replay_saver.player_name, "한국");
+    assert_eq!(guessed.game_header.replay_saver.raw_player_name, Some(korean.clone()));
+    assert_eq!(first_chat(&guessed).1, Some(cyrillic.clone()));
+    assert!(guessed.game_header.players.iter().all(|player| player.raw_player_name.is_none()));
+
+    let fixed = parse(StringEncoding::Codepage(Codepage::Cyrillic)).unwrap();
+    assert_eq!(first_chat(&fixed), (String::from("Привет"), Some(cyrillic.clone())));
+
+    /* Written back out with the original bytes */
+    let mut rewritten = Vec::new();
+    w3g_common::parser::write_replay(&guessed, &mut rewritten).unwrap();
+    assert_eq!(rewritten, written);
+
+    /* Unless the string was edited, the stale bytes are dropped */
+    guessed.game_header.replay_saver.player_name = String::from("Renamed");
+    let mut rewritten = Vec::new();
+    w3g_common::parser::write_replay(&guessed, &mut rewritten).unwrap();
+    let options = ParseOptions { string_encoding: StringEncoding::Guess, ..ParseOptions::default() };
+    let edited = w3g_common::parser::parse_replay_with_options(&mut Cursor::new(rewritten), options).unwrap();
+    assert_eq!(edited.game_header.replay_saver.player_name, "Renamed");
+    assert_eq!(edited.game_header.replay_saver.raw_player_name, None);
+
+    let options = ParseOptions { string_encoding: StringEncoding::Codepage(Codepage::Cyrillic), ..ParseOptions::default() };
+    let data = w3g_common::parser::parse_replay_bytes_with_options(&written, options).unwrap();
+    match data.blocks().next()
+    {
+        Some(Ok(ReplayBlockView::PlayerChat { message, raw_message, .. })) => assert_eq!((message.as_ref(), raw_message), ("Привет", &cyrillic[..])),
+        block => panic!("{:?} is not the chat message", block),
+    }
+}
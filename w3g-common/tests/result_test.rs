@@ -0,0 +1,122 @@
+extern crate w3g_common;
+
+use w3g_common::parser::{ReplayBlock, Action, GameSettings};
+use w3g_common::pubsub::model::{Player, IdTeam};
+use w3g_common::result::{ResultExtractor, Outcome, Confidence, extract_game_result, extract_game_result_or, extractor_for_map};
+use w3g_common::result::island_defense::{IslandDefense, BUILDER_TEAM, TITAN_TEAM, id_game_result};
+
+mod common;
+use common::{player_list, push_actions};
+
+#[test]
+fn test_island_defense_result_11151811()
+{
+    let replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let players = player_list(&replay);
+
+    let settings = replay.game_header.game_settings().unwrap();
+    assert!(IslandDefense.handles_map(&settings));
+    assert!(extractor_for_map(&settings).is_some());
+
+    let result = extract_game_result(&players, &replay).unwrap();
+    assert_eq!(result.confidence, Confidence::Reported);
+    assert_eq!(result.outcome, Outcome::Winner(0));
+    assert_eq!(result.winner().unwrap().name, BUILDER_TEAM);
+
+    /* Slot 10, who joined first */
+    let titans = result.team(TITAN_TEAM).unwrap();
+    assert_eq!(titans.players.len(), 1);
+    assert_eq!(titans.players[0].role, "Titan");
+    assert_eq!(titans.players[0].player.name, "Kaltecp");
+
+    let builders = result.team(BUILDER_TEAM).unwrap();
+    assert_eq!(builders.players.len(), players.len() - 1);
+    assert_eq!(builders.players[0].player.name, "Demonic_Bread");
+    assert!(builders.players.iter().all(|player_role| player_role.player.name != "Kaltecp"));
+
+    let id_result = id_game_result(&result).unwrap();
+    assert_eq!(id_result.winner, IdTeam::Builder);
+    assert_eq!(id_result.titans, vec![Player::new("Kaltecp", "USEast")]);
+
+    /* The list is lined up with the replay's players so it can't be missing anyone */
+    let mut missing_players = players.clone();
+    missing_players.pop();
+    assert!(extract_game_result(&missing_players, &replay).is_err());
+}
+
+#[test]
+fn test_island_defense_result_without_flags()
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let players = player_list(&replay);
+
+    for block in replay.replay_blocks.iter_mut()
+    {
+        if let ReplayBlock::Tick { commands, .. } = block
+        {
+            for command in commands.iter_mut()
+            {
+                command.actions.retain(|action| match action
+                {
+                    Action::SyncStoredInteger { group, .. } => group != "flag",
+                    _ => true,
+                });
+            }
+        }
+    }
+
+    let result = extract_game_result(&players, &replay).unwrap();
+    assert_eq!(result.outcome, Outcome::Tie);
+    assert_eq!(result.confidence, Confidence::Assumed);
+    assert_eq!(id_game_result(&result).unwrap().winner, IdTeam::Tie);
+}
+
+#[test]
+fn test_island_defense_titan_time_limit()
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let players = player_list(&replay);
+
+    /* The titan wins after the builders' flags, but too late */
+    push_actions(&mut replay, 1, vec![Action::SyncStoredInteger { file: String::from("ID.D"), group: String::from("flag"), key: String::from("10"), value: 1 }]);
+    replay.replay_header.duration = 2 * 60 * 60 * 1000;
+
+    let result = extract_game_result(&players, &replay).unwrap();
+    assert_eq!(result.outcome, Outcome::Tie);
+    assert_eq!(result.confidence, Confidence::Reported);
+}
+
+#[test]
+fn test_no_extractor_for_unknown_map()
+{
+    let replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let mut settings = replay.game_header.game_settings().unwrap();
+    settings.map_path = String::from("Maps\\FrozenThrone\\(2)EchoIsles.w3x");
+
+    assert!(extractor_for_map(&settings).is_none());
+}
+
+#[test]
+fn test_fallback_for_unknown_map()
+{
+    let mut replay = w3g_common::parser::extract_replay("resources/11151811.w3g").unwrap();
+    let players = player_list(&replay);
+    let result = extract_game_result(&players, &replay).unwrap();
+
+    /* 'I' & 'K' are both odd so they're encoded the same way, swapping them renames the map without touching the masks */
+    let encoded_string = replay.game_header.encoded_string.clone();
+    let index = (0..encoded_string.len())
+        .filter(|&index| encoded_string[index] == b'I')
+        .find(|&index|
+        {
+            let mut renamed = encoded_string.clone();
+            renamed[index] = b'K';
+            GameSettings::decode(&renamed).map(|settings| settings.map_file_name() == "KDProt4.0.1.w3x").unwrap_or(false)
+        })
+        .unwrap();
+    replay.game_header.encoded_string[index] = b'K';
+
+    assert!(extractor_for_map(&replay.game_header.game_settings().unwrap()).is_none());
+    assert!(extract_game_result(&players, &replay).is_err());
+    assert_eq!(extract_game_result_or(&players, &replay, &IslandDefense).unwrap(), result);
+}
@@ -39,7 +39,7 @@ use w3g_common::errors::Result;
 use w3g_common::pubsub::model::{Player, Message};
 use w3g_common::pubsub::producer::PubSubProducer;
 use w3g_common::pubsub::ID_REPLAY_TOPIC;
-use w3g_common::parser::Replay; 
+use w3g_common::parser::{Replay, ParseOptions, StringEncoding}; 
 
 
 use std::env; 
@@ -171,8 +171,11 @@ impl GameIdDto
         file.sync_all()?
     }
  
+    /* Plenty of players on ENT have names in their own codepage, which would fail the whole replay */
+    let options = ParseOptions { string_encoding: StringEncoding::Guess, ..ParseOptions::default() };
+
     let mut replay_cursor = Cursor::new(replay_bytes);
-    Ok((players, w3g_common::parser::parse_replay(&mut replay_cursor)?))
+    Ok((players, w3g_common::parser::parse_replay_with_options(&mut replay_cursor, options)?))
  }
 
 fn store_game_id(dto: GameIdDto, collection: &Collection)